@@ -0,0 +1,207 @@
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{
+        CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseFollowup,
+    },
+    client::Context,
+    model::Permissions,
+};
+
+use crate::db::PERSISTENT_DB;
+use crate::settings::{find_key, GuildSettings, Key, Kind, KEYS};
+use crate::voicevox::{model::SpeakerId, Client as VoicevoxClient};
+
+use super::simple_resp_helper;
+
+/// Characters allowed in a message.
+const MESSAGE_LIMIT: usize = 2000;
+
+pub fn register(prefix: &str) -> CreateCommand {
+    CreateCommand::new(format!("{prefix}config"))
+        .description("Server settings")
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "get", "Show settings")
+                .add_sub_option(key_option(false)),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Change a setting")
+                .add_sub_option(key_option(true))
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "value", "New value")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "reset",
+                "Restore the default (all settings if key is omitted)",
+            )
+            .add_sub_option(key_option(false)),
+        )
+}
+
+fn key_option(required: bool) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, "key", "Setting")
+        .set_autocomplete(true)
+        .required(required)
+}
+
+fn find<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    options.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+fn describe(settings: &GuildSettings, key: &Key) -> String {
+    format!("`{}` = `{}`", key.name, settings.get(key))
+}
+
+pub async fn run(ctx: &Context, interaction: CommandInteraction, voicevox: &VoicevoxClient) {
+    let guild_id = interaction.guild_id.unwrap();
+    let option = &interaction.data.options.first().unwrap();
+
+    let CommandDataOptionValue::SubCommand(c) = &option.value else {
+        simple_resp_helper(&interaction, ctx, "Unknown Error", true).await;
+        return;
+    };
+
+    let key = match find(c, "key").and_then(CommandDataOptionValue::as_str) {
+        Some(name) => {
+            let Some(key) = find_key(name) else {
+                simple_resp_helper(&interaction, ctx, &format!("Unknown key: {name}"), true).await;
+                return;
+            };
+
+            Some(key)
+        }
+        None => None,
+    };
+
+    let mut settings = PERSISTENT_DB.get_guild_settings(guild_id);
+
+    let result = match (option.name.as_str(), key) {
+        ("get", Some(key)) => {
+            simple_resp_helper(&interaction, ctx, &describe(&settings, key), true).await;
+            return;
+        }
+        ("get", None) => {
+            let lines: Vec<String> = KEYS.iter().map(|k| describe(&settings, k)).collect();
+            let mut messages = chunks(&lines).into_iter();
+
+            simple_resp_helper(
+                &interaction,
+                ctx,
+                &messages.next().unwrap_or_default(),
+                true,
+            )
+            .await;
+
+            for message in messages {
+                let followup = CreateInteractionResponseFollowup::new()
+                    .content(message)
+                    .ephemeral(true);
+
+                if let Err(e) = interaction.create_followup(ctx, followup).await {
+                    println!("Failed to send the settings: {e:?}");
+                }
+            }
+            return;
+        }
+        ("set", Some(key)) => {
+            let value = find(c, "value")
+                .and_then(CommandDataOptionValue::as_str)
+                .unwrap();
+
+            set(&mut settings, key, value, voicevox).map(|()| describe(&settings, key))
+        }
+        ("reset", Some(key)) => {
+            settings.reset(key);
+            Ok(describe(&settings, key))
+        }
+        ("reset", None) => {
+            settings = GuildSettings::default();
+            Ok("All settings have been reset.".to_string())
+        }
+        _ => Err("Unknown Error".to_string()),
+    };
+
+    match result {
+        Ok(message) => {
+            PERSISTENT_DB.store_guild_settings(guild_id, settings);
+            simple_resp_helper(&interaction, ctx, &message, false).await;
+        }
+        Err(message) => simple_resp_helper(&interaction, ctx, &message, true).await,
+    }
+}
+
+fn set(
+    settings: &mut GuildSettings,
+    key: &Key,
+    value: &str,
+    voicevox: &VoicevoxClient,
+) -> Result<(), String> {
+    if key.kind == Kind::Speaker
+        && value
+            .parse::<SpeakerId>()
+            .ok()
+            .and_then(|id| voicevox.query_style_by_id(id))
+            .is_none()
+    {
+        return Err(format!("Unknown speaker: {value}"));
+    }
+
+    settings.set(key, value)
+}
+
+/// Joins `lines` into as few messages as fit in Discord's limit, splitting lines too long by themselves.
+fn chunks(lines: &[String]) -> Vec<String> {
+    let mut messages = vec![];
+    let mut current = String::new();
+
+    for line in lines {
+        let mut chars = line.chars().peekable();
+
+        while chars.peek().is_some() {
+            let part: String = chars.by_ref().take(MESSAGE_LIMIT).collect();
+
+            if !current.is_empty()
+                && current.chars().count() + 1 + part.chars().count() > MESSAGE_LIMIT
+            {
+                messages.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&part);
+        }
+    }
+
+    if !current.is_empty() {
+        messages.push(current);
+    }
+
+    messages
+}
+
+pub async fn autocomplete(ctx: &Context, interaction: CommandInteraction) {
+    let typed = interaction
+        .data
+        .autocomplete()
+        .map(|o| o.value.to_string())
+        .unwrap_or_default();
+
+    let response = KEYS
+        .iter()
+        .filter(|k| k.name.contains(&typed))
+        .take(25)
+        .fold(CreateAutocompleteResponse::new(), |r, k| {
+            r.add_string_choice(format!("{} - {}", k.name, k.description), k.name)
+        });
+
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Autocomplete(response))
+        .await
+        .expect("Failed to write response");
+}
@@ -45,6 +45,22 @@ pub fn register(prefix: &str) -> CreateCommand {
                     .required(true),
             ),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "emoji",
+                "Set how a custom emoji of this server is read",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "emoji", "Emoji or its name")
+                    .required(true),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "reading",
+                "Reading of the emoji (remove the reading if omitted)",
+            )),
+        )
 }
 
 pub async fn run(ctx: &Context, interaction: CommandInteraction) {
@@ -90,6 +106,33 @@ pub async fn run(ctx: &Context, interaction: CommandInteraction) {
             }
             _ => simple_resp_helper(&interaction, ctx, "Unknown Error", true).await,
         },
+        "emoji" => match (&option.value, interaction.guild_id) {
+            (serenity::all::CommandDataOptionValue::SubCommand(c), Some(guild_id)) => {
+                let name = emoji_name(c.first().unwrap().value.as_str().unwrap());
+
+                let message = if let Some(reading) = c.get(1).and_then(|o| o.value.as_str()) {
+                    PERSISTENT_DB.store_emoji_reading(guild_id, name, reading);
+                    format!("Added :{name}: => {reading}")
+                } else {
+                    PERSISTENT_DB.remove_emoji_reading(guild_id, name);
+                    format!("Removed :{name}:")
+                };
+
+                simple_resp_helper(&interaction, ctx, &message, false).await;
+            }
+            (_, None) => {
+                simple_resp_helper(&interaction, ctx, "Only available in servers", true).await;
+            }
+            _ => simple_resp_helper(&interaction, ctx, "Unknown Error", true).await,
+        },
         _ => simple_resp_helper(&interaction, ctx, "Unknown Error", true).await,
     }
 }
+
+/// Accepts `name`, `:name:` and `<:name:id>`.
+fn emoji_name(input: &str) -> &str {
+    let s = input.trim().trim_start_matches('<').trim_end_matches('>');
+    let s = s.strip_prefix("a:").unwrap_or(s);
+
+    s.trim_matches(':').split(':').next().unwrap_or(s)
+}
@@ -1,13 +1,22 @@
+use std::collections::HashSet;
+
 use serenity::{
     builder::CreateCommand,
     client::Context,
-    model::{application::CommandInteraction, id::ChannelId, prelude::Mentionable, Permissions},
+    model::{
+        application::CommandInteraction,
+        id::{ChannelId, GuildId},
+        prelude::Mentionable,
+        Permissions,
+    },
 };
 use songbird::CoreEvent;
 
 use crate::commands::simple_resp_helper;
-use crate::db::INMEMORY_DB;
+use crate::db::{Session, INMEMORY_DB, PERSISTENT_DB};
+use crate::settings::JoinChannels;
 use crate::songbird_handler::DriverDisconnectNotifier;
+use crate::voice_state;
 
 pub fn register(prefix: &str) -> CreateCommand {
     CreateCommand::new(format!("{prefix}join"))
@@ -16,7 +25,7 @@ pub fn register(prefix: &str) -> CreateCommand {
 }
 
 #[allow(clippy::enum_variant_names)]
-enum JoinError {
+pub enum JoinError {
     YouAreNotInVoiceChannel,
     FailedToJoinVoiceChannel,
     CannotAccessToTextChannel(ChannelId),
@@ -24,7 +33,7 @@ enum JoinError {
 }
 
 impl JoinError {
-    fn to_message(&self) -> String {
+    pub fn to_message(&self) -> String {
         match self {
             Self::YouAreNotInVoiceChannel => "You are not in voice channel".to_string(),
             Self::FailedToJoinVoiceChannel => "Failed to join to voice channel".to_string(),
@@ -38,7 +47,7 @@ impl JoinError {
 async fn run_(
     ctx: &Context,
     interaction: &CommandInteraction,
-) -> Result<(ChannelId, ChannelId), JoinError> {
+) -> Result<(Vec<ChannelId>, ChannelId), JoinError> {
     if !interaction
         .app_permissions
         .unwrap()
@@ -47,18 +56,45 @@ async fn run_(
         return Err(JoinError::CannotAccessToTextChannel(interaction.channel_id));
     }
 
-    let guild = ctx
+    let guild_id = interaction.guild_id.unwrap();
+
+    let vc = ctx
         .cache
-        .guild(interaction.guild_id.unwrap())
+        .guild(guild_id)
         .unwrap()
-        .clone();
-
-    let vc = guild
         .voice_states
         .get(&interaction.user.id)
-        .map(|v| guild.channels.get(&v.channel_id.unwrap()).unwrap().clone())
+        .and_then(|v| v.channel_id)
         .ok_or(JoinError::YouAreNotInVoiceChannel)?;
 
+    let texts = match PERSISTENT_DB.get_guild_settings(guild_id).join_channels {
+        JoinChannels::Command => vec![interaction.channel_id],
+        JoinChannels::Voice => vec![vc],
+        JoinChannels::Both => vec![interaction.channel_id, vc],
+    };
+
+    connect(ctx, guild_id, vc, &texts).await?;
+
+    Ok((texts, vc))
+}
+
+/// Joins `voice` and starts reading `texts`.
+/// Channels linked so far are kept if the bot is already in `voice`.
+pub async fn connect(
+    ctx: &Context,
+    guild_id: GuildId,
+    voice: ChannelId,
+    texts: &[ChannelId],
+) -> Result<(), JoinError> {
+    let vc = ctx
+        .cache
+        .guild(guild_id)
+        .unwrap()
+        .channels
+        .get(&voice)
+        .ok_or(JoinError::CannotAccessToVoiceChannel(voice))?
+        .clone();
+
     if !vc
         .permissions_for_user(&ctx.cache, ctx.cache.current_user().id)
         .unwrap()
@@ -67,9 +103,15 @@ async fn run_(
         return Err(JoinError::CannotAccessToVoiceChannel(vc.id));
     }
 
+    let mut linked = if voice_state::current_channel(ctx, guild_id).await == Some(voice) {
+        INMEMORY_DB.get_instance(guild_id).unwrap_or_default()
+    } else {
+        HashSet::new()
+    };
+
     let manager = songbird::get(ctx).await.unwrap();
 
-    if let Some(h) = manager.get(guild.id) {
+    if let Some(h) = manager.get(guild_id) {
         h.lock()
             .await
             .join(vc.id)
@@ -77,7 +119,7 @@ async fn run_(
             .map_err(|_| JoinError::FailedToJoinVoiceChannel)?;
     } else {
         let h = manager
-            .join(guild.id, vc.id)
+            .join(guild_id, vc.id)
             .await
             .map_err(|_| JoinError::FailedToJoinVoiceChannel)?;
 
@@ -89,18 +131,41 @@ async fn run_(
         );
     }
 
-    INMEMORY_DB.store_instance(guild.id, interaction.channel_id);
+    linked.extend(texts);
 
-    Ok((interaction.channel_id, vc.id))
+    INMEMORY_DB.store_instance(guild_id, linked.clone());
+    PERSISTENT_DB.store_session(
+        guild_id,
+        Session {
+            voice,
+            texts: linked,
+        },
+    );
+
+    Ok(())
+}
+
+/// Writes the channels currently linked in the guild through to the persisted session.
+pub async fn save_session(ctx: &Context, guild_id: GuildId) {
+    let (Some(voice), Some(texts)) = (
+        voice_state::current_channel(ctx, guild_id).await,
+        INMEMORY_DB.get_instance(guild_id),
+    ) else {
+        return;
+    };
+
+    PERSISTENT_DB.store_session(guild_id, Session { voice, texts });
 }
 
 pub async fn run(ctx: &Context, interaction: CommandInteraction) {
     match run_(ctx, &interaction).await {
-        Ok((text, voice)) => {
+        Ok((texts, voice)) => {
+            let texts: Vec<String> = texts.iter().map(|t| t.mention().to_string()).collect();
+
             simple_resp_helper(
                 &interaction,
                 ctx,
-                &format!("Linked! {} <-> {}", text.mention(), voice.mention()),
+                &format!("Linked! {} <-> {}", texts.join(" "), voice.mention()),
                 false,
             )
             .await;
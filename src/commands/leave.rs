@@ -1,6 +1,9 @@
 use serenity::{builder::CreateCommand, client::Context, model::application::CommandInteraction};
 
-use crate::{commands::simple_resp_helper, db::INMEMORY_DB};
+use crate::{
+    commands::simple_resp_helper,
+    db::{INMEMORY_DB, PERSISTENT_DB},
+};
 
 pub fn register(prefix: &str) -> CreateCommand {
     CreateCommand::new(format!("{prefix}leave"))
@@ -21,6 +24,7 @@ pub async fn run(ctx: &Context, interaction: CommandInteraction) {
     };
 
     INMEMORY_DB.destroy_instance(guild_id);
+    PERSISTENT_DB.remove_session(guild_id);
 
     simple_resp_helper(&interaction, ctx, "Connection has been closed.", false).await;
 }
@@ -0,0 +1,49 @@
+use serenity::{
+    all::{ChannelType, CommandInteraction, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
+    client::Context,
+    model::prelude::Mentionable,
+};
+
+use crate::commands::{join, simple_resp_helper};
+use crate::db::INMEMORY_DB;
+
+pub fn register(prefix: &str) -> CreateCommand {
+    CreateCommand::new(format!("{prefix}link"))
+        .description("Read another text channel as well")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "channel",
+                "Text channel to read (defaults to this channel)",
+            )
+            .channel_types(vec![ChannelType::Text, ChannelType::Voice]),
+        )
+}
+
+pub async fn run(ctx: &Context, interaction: CommandInteraction) {
+    let guild_id = interaction.guild_id.unwrap();
+
+    let channel = interaction
+        .data
+        .options
+        .first()
+        .and_then(|o| o.value.as_channel_id())
+        .unwrap_or(interaction.channel_id);
+
+    if !INMEMORY_DB.link_channel(guild_id, channel) {
+        simple_resp_helper(&interaction, ctx, "Not in a voice channel.", true).await;
+        return;
+    }
+
+    join::save_session(ctx, guild_id).await;
+
+    simple_resp_helper(
+        &interaction,
+        ctx,
+        &format!("Linked {}", channel.mention()),
+        false,
+    )
+    .await;
+}
@@ -5,11 +5,16 @@ use serenity::{
     model::application::CommandInteraction,
 };
 
+pub mod config;
+pub mod dict;
 pub mod join;
 pub mod leave;
+pub mod link;
 pub mod skip;
+pub mod sozai;
 pub mod speaker;
-pub mod dict;
+pub mod unlink;
+pub mod yomi;
 
 async fn simple_resp_helper(
     interaction: &CommandInteraction,
@@ -0,0 +1,84 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, UserId},
+    builder::{CreateCommand, CreateCommandOption, EditInteractionResponse},
+    client::Context,
+    model::Permissions,
+};
+
+use crate::config::CONFIG;
+use crate::db::INMEMORY_DB;
+use crate::sozai::Source;
+
+use super::simple_resp_helper;
+
+pub fn register(prefix: &str) -> CreateCommand {
+    CreateCommand::new(format!("{prefix}sozai"))
+        .description("Sound effects")
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "reload",
+            "Read the index of sound effects again",
+        ))
+}
+
+pub async fn run(ctx: &Context, interaction: CommandInteraction) {
+    let option = &interaction.data.options.first().unwrap();
+
+    if option.name != "reload" {
+        simple_resp_helper(&interaction, ctx, "Unknown Error", true).await;
+        return;
+    }
+
+    // Fetching the index can take longer than an interaction may wait for a response.
+    if let Err(e) = interaction.defer_ephemeral(ctx).await {
+        println!("Failed to defer the response: {e:?}");
+        return;
+    }
+
+    let message = if is_admin(ctx, interaction.user.id).await {
+        let source = Source::parse(&CONFIG.sozai_index_url);
+
+        match INMEMORY_DB.refresh_sozai(&source).await {
+            Ok(changes) if changes.is_empty() => "Already up to date".to_string(),
+            Ok(changes) => format!("Reloaded: {changes}"),
+            Err(e) => format!("Failed to reload, the last index is kept: {e}"),
+        }
+    } else {
+        // The index is shared by every server, so managing one is not enough.
+        "Only the owners of the bot can reload sound effects".to_string()
+    };
+
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(message))
+        .await
+    {
+        println!("Failed to write response: {e:?}");
+    }
+}
+
+/// Whether `user` owns the application or is listed in `ADMINS`.
+async fn is_admin(ctx: &Context, user: UserId) -> bool {
+    let listed = CONFIG.admins.as_deref().unwrap_or_default().split(',');
+
+    if listed
+        .filter_map(|id| id.trim().parse().ok())
+        .any(|id: u64| id == user.get())
+    {
+        return true;
+    }
+
+    match ctx.http.get_current_application_info().await {
+        Ok(info) => {
+            info.owner.is_some_and(|o| o.id == user)
+                || info
+                    .team
+                    .is_some_and(|t| t.members.iter().any(|m| m.user.id == user))
+        }
+        Err(e) => {
+            println!("Failed to get the application: {e:?}");
+            false
+        }
+    }
+}
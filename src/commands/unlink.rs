@@ -0,0 +1,71 @@
+use serenity::{
+    all::{ChannelType, CommandInteraction, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
+    client::Context,
+    model::prelude::Mentionable,
+};
+
+use crate::commands::{join, simple_resp_helper};
+use crate::db::INMEMORY_DB;
+
+pub fn register(prefix: &str) -> CreateCommand {
+    CreateCommand::new(format!("{prefix}unlink"))
+        .description("Stop reading a text channel")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "channel",
+                "Text channel to stop reading (defaults to this channel)",
+            )
+            .channel_types(vec![ChannelType::Text, ChannelType::Voice]),
+        )
+}
+
+pub async fn run(ctx: &Context, interaction: CommandInteraction) {
+    let guild_id = interaction.guild_id.unwrap();
+
+    let channel = interaction
+        .data
+        .options
+        .first()
+        .and_then(|o| o.value.as_channel_id())
+        .unwrap_or(interaction.channel_id);
+
+    let Some(linked) = INMEMORY_DB.get_instance(guild_id) else {
+        simple_resp_helper(&interaction, ctx, "Not in a voice channel.", true).await;
+        return;
+    };
+
+    if linked.len() == 1 && linked.contains(&channel) {
+        simple_resp_helper(
+            &interaction,
+            ctx,
+            "Cannot unlink the last channel. Use leave instead.",
+            true,
+        )
+        .await;
+        return;
+    }
+
+    if !INMEMORY_DB.unlink_channel(guild_id, channel) {
+        simple_resp_helper(
+            &interaction,
+            ctx,
+            &format!("{} is not linked.", channel.mention()),
+            true,
+        )
+        .await;
+        return;
+    }
+
+    join::save_session(ctx, guild_id).await;
+
+    simple_resp_helper(
+        &interaction,
+        ctx,
+        &format!("Unlinked {}", channel.mention()),
+        false,
+    )
+    .await;
+}
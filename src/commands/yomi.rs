@@ -0,0 +1,99 @@
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
+    client::Context,
+    model::{prelude::Mentionable, Permissions},
+};
+
+use crate::db::PERSISTENT_DB;
+
+use super::simple_resp_helper;
+
+pub fn register(prefix: &str) -> CreateCommand {
+    CreateCommand::new(format!("{prefix}yomi"))
+        .description("Set how names are read")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                "Set how a name is read",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "reading", "Reading")
+                    .required(true),
+            )
+            .add_sub_option(user_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Read a name as it is written again",
+            )
+            .add_sub_option(user_option()),
+        )
+}
+
+fn user_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::User,
+        "user",
+        "Member other than you (requires Manage Nicknames)",
+    )
+}
+
+fn find<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    options.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+pub async fn run(ctx: &Context, interaction: CommandInteraction) {
+    let guild_id = interaction.guild_id.unwrap();
+    let option = &interaction.data.options.first().unwrap();
+
+    let CommandDataOptionValue::SubCommand(options) = &option.value else {
+        simple_resp_helper(&interaction, ctx, "Unknown Error", true).await;
+        return;
+    };
+
+    let user = match find(options, "user") {
+        Some(CommandDataOptionValue::User(user)) => *user,
+        _ => interaction.user.id,
+    };
+
+    if user != interaction.user.id && !is_moderator(&interaction) {
+        simple_resp_helper(
+            &interaction,
+            ctx,
+            "Manage Nicknames permission is required to set names of others",
+            true,
+        )
+        .await;
+        return;
+    }
+
+    let message = match (option.name.as_str(), find(options, "reading")) {
+        ("set", Some(CommandDataOptionValue::String(reading))) if !reading.trim().is_empty() => {
+            PERSISTENT_DB.store_name_reading(guild_id, user, reading.trim());
+            format!("{} => {}", user.mention(), reading.trim())
+        }
+        ("remove", _) => {
+            PERSISTENT_DB.remove_name_reading(guild_id, user);
+            format!("Removed the reading of {}", user.mention())
+        }
+        _ => {
+            simple_resp_helper(&interaction, ctx, "Unknown Error", true).await;
+            return;
+        }
+    };
+
+    simple_resp_helper(&interaction, ctx, &message, true).await;
+}
+
+fn is_moderator(interaction: &CommandInteraction) -> bool {
+    interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.contains(Permissions::MANAGE_NICKNAMES))
+}
@@ -10,8 +10,23 @@ pub static CONFIG: Lazy<Config> =
 pub struct Config {
     pub command_prefix: Option<String>,
     pub voicevox_host: String,
+    /// Engines other than VOICEVOX as `name=url` separated by `;`, see [`crate::tts::HttpEngine`].
+    pub tts_engines: Option<String>,
     pub discord_token: String,
     pub additional_headers: Option<String>,
     pub persistent_path: PathBuf,
+    /// URL or local path of the sozai index.
     pub sozai_index_url: String,
+    /// Seconds between refreshes of the sozai index, an hour if unset. `0` turns them off.
+    pub sozai_refresh_secs: Option<u64>,
+    /// Where sozai are cached, `sozai` next to `persistent_path` if unset.
+    pub sozai_cache_dir: Option<PathBuf>,
+    /// Bytes of sozai cached on disk, 512 MiB if unset.
+    pub sozai_cache_bytes: Option<u64>,
+    /// How many of the most played sozai are cached ahead of time, 50 if unset.
+    pub sozai_prefetch: Option<usize>,
+    /// User IDs separated by `,` allowed to run commands affecting every server, besides the owners of the application.
+    pub admins: Option<String>,
+    /// Seconds to let utterances being played finish on shutdown.
+    pub shutdown_timeout_secs: Option<u64>,
 }
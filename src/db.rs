@@ -1,13 +1,22 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
+use moka::future::Cache;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+use songbird::tracks::TrackHandle;
+use tokio::task::AbortHandle;
+use unicode_segmentation::UnicodeSegmentation;
 
+use crate::replacer::Replacer;
+use crate::settings::GuildSettings;
 use crate::sozai;
 use crate::voicevox::model::SpeakerId;
 
@@ -19,21 +28,41 @@ pub static PERSISTENT_DB: Lazy<PersistentDB> = Lazy::new(|| {
 struct PersistentStructure {
     voice_settings: HashMap<UserId, SpeakerId>,
     dictionary: HashMap<String, String>,
+    #[serde(default)]
+    guild_settings: HashMap<GuildId, GuildSettings>,
+    #[serde(default)]
+    sessions: HashMap<GuildId, Session>,
+    /// Readings of custom emoji by name.
+    #[serde(default)]
+    emoji_dictionary: HashMap<GuildId, HashMap<String, String>>,
+    /// How members' names are read, by guild.
+    #[serde(default)]
+    name_readings: HashMap<GuildId, HashMap<UserId, String>>,
+}
+
+/// A voice connection which should survive restarts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub voice: ChannelId,
+    pub texts: HashSet<ChannelId>,
 }
 
 pub struct PersistentDB {
     file: PathBuf,
     data: RwLock<PersistentStructure>,
+    /// Built from `data.dictionary`, and rebuilt whenever it changes.
+    dictionary_replacer: RwLock<Arc<Replacer>>,
 }
 
 impl PersistentDB {
     fn new(file: &Path) -> anyhow::Result<Self> {
-        let data =
+        let data: PersistentStructure =
             serde_json::from_reader(BufReader::new(File::open(file)?)).expect("DB is corrupt");
 
         Ok(Self {
             file: file.into(),
-            data,
+            dictionary_replacer: RwLock::new(Arc::new(Replacer::new(&data.dictionary))),
+            data: RwLock::new(data),
         })
     }
 
@@ -57,17 +86,8 @@ impl PersistentDB {
         self.flush();
     }
 
-    pub fn get_dictionary_word(&self, word: &str) -> Option<String> {
-        self.data
-            .read()
-            .unwrap()
-            .dictionary
-            .get(word)
-            .map(ToOwned::to_owned)
-    }
-
-    pub fn get_dictionary(&self) -> HashMap<String, String> {
-        self.data.read().unwrap().dictionary.clone()
+    pub fn dictionary_replacer(&self) -> Arc<Replacer> {
+        self.dictionary_replacer.read().unwrap().clone()
     }
 
     pub fn store_dictionary_word(&self, word: &str, replacement: &str) {
@@ -77,16 +97,120 @@ impl PersistentDB {
             .dictionary
             .insert(word.to_owned(), replacement.to_owned());
 
+        self.rebuild_dictionary_replacer();
         self.flush();
     }
 
     pub fn remove_dictionary_word(&self, word: &str) {
         self.data.write().unwrap().dictionary.remove(word);
 
+        self.rebuild_dictionary_replacer();
+        self.flush();
+    }
+
+    fn rebuild_dictionary_replacer(&self) {
+        let replacer = Replacer::new(&self.data.read().unwrap().dictionary);
+
+        *self.dictionary_replacer.write().unwrap() = Arc::new(replacer);
+    }
+
+    pub fn get_emoji_dictionary(&self, guild: GuildId) -> HashMap<String, String> {
+        self.data
+            .read()
+            .unwrap()
+            .emoji_dictionary
+            .get(&guild)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn store_emoji_reading(&self, guild: GuildId, name: &str, reading: &str) {
+        self.data
+            .write()
+            .unwrap()
+            .emoji_dictionary
+            .entry(guild)
+            .or_default()
+            .insert(name.to_owned(), reading.to_owned());
+
+        self.flush();
+    }
+
+    pub fn remove_emoji_reading(&self, guild: GuildId, name: &str) {
+        if let Some(d) = self.data.write().unwrap().emoji_dictionary.get_mut(&guild) {
+            d.remove(name);
+        }
+
         self.flush();
     }
 
-    fn flush(&self) {
+    pub fn get_name_reading(&self, guild: GuildId, user: UserId) -> Option<String> {
+        self.data
+            .read()
+            .unwrap()
+            .name_readings
+            .get(&guild)?
+            .get(&user)
+            .cloned()
+    }
+
+    pub fn store_name_reading(&self, guild: GuildId, user: UserId, reading: &str) {
+        self.data
+            .write()
+            .unwrap()
+            .name_readings
+            .entry(guild)
+            .or_default()
+            .insert(user, reading.to_owned());
+
+        self.flush();
+    }
+
+    pub fn remove_name_reading(&self, guild: GuildId, user: UserId) {
+        if let Some(r) = self.data.write().unwrap().name_readings.get_mut(&guild) {
+            r.remove(&user);
+        }
+
+        self.flush();
+    }
+
+    pub fn get_guild_settings(&self, guild: GuildId) -> GuildSettings {
+        self.data
+            .read()
+            .unwrap()
+            .guild_settings
+            .get(&guild)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn store_guild_settings(&self, guild: GuildId, settings: GuildSettings) {
+        self.data
+            .write()
+            .unwrap()
+            .guild_settings
+            .insert(guild, settings);
+
+        self.flush();
+    }
+
+    pub fn get_sessions(&self) -> HashMap<GuildId, Session> {
+        self.data.read().unwrap().sessions.clone()
+    }
+
+    pub fn store_session(&self, guild: GuildId, session: Session) {
+        self.data.write().unwrap().sessions.insert(guild, session);
+
+        self.flush();
+    }
+
+    pub fn remove_session(&self, guild: GuildId) {
+        if self.data.write().unwrap().sessions.remove(&guild).is_some() {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&self) {
         File::create(&self.file)
             .expect("Failed to create renew file.")
             .write_all(
@@ -99,14 +223,31 @@ impl PersistentDB {
 }
 
 struct InmemoryStructure {
-    instances: HashMap<GuildId, ChannelId>,
-    sozai_map: HashMap<String, String>,
+    instances: HashMap<GuildId, HashSet<ChannelId>>,
+    sozai: sozai::Index,
+    /// Tasks waiting to leave a voice channel nobody is in, at most one per guild.
+    leaves: HashMap<GuildId, AbortHandle>,
+}
+
+/// A message which has been handed to songbird, kept around so that edits can refer to it.
+#[derive(Clone)]
+pub struct Utterance {
+    /// The filtered content of the message.
+    pub text: String,
+    pub track: TrackHandle,
+    /// Whether `track` reads a correction rather than the message itself.
+    pub correction: bool,
 }
 
 pub struct InmemoryDB {
     data: RwLock<InmemoryStructure>,
+    utterances: Cache<MessageId, Utterance>,
+    shutting_down: AtomicBool,
 }
 
+/// Edits made later than this are not read.
+const UTTERANCE_TTL_MINUTES: u64 = 30;
+
 pub static INMEMORY_DB: Lazy<InmemoryDB> = Lazy::new(InmemoryDB::new);
 
 impl InmemoryDB {
@@ -114,12 +255,27 @@ impl InmemoryDB {
         Self {
             data: RwLock::new(InmemoryStructure {
                 instances: HashMap::new(),
-                sozai_map: HashMap::new(),
+                sozai: sozai::Index::default(),
+                leaves: HashMap::new(),
             }),
+            utterances: Cache::builder()
+                .max_capacity(1000)
+                .time_to_live(Duration::from_secs(UTTERANCE_TTL_MINUTES * 60))
+                .build(),
+            shutting_down: AtomicBool::new(false),
         }
     }
 
-    pub fn get_instance(&self, guild_id: GuildId) -> Option<ChannelId> {
+    pub fn start_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Once set, no new utterance is accepted and voice sessions are kept for the next start.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    pub fn get_instance(&self, guild_id: GuildId) -> Option<HashSet<ChannelId>> {
         self.data
             .read()
             .unwrap()
@@ -128,30 +284,82 @@ impl InmemoryDB {
             .map(ToOwned::to_owned)
     }
 
-    pub fn store_instance(&self, guild_id: GuildId, channel_id: ChannelId) {
+    pub fn store_instance(&self, guild_id: GuildId, channel_ids: HashSet<ChannelId>) {
         self.data
             .write()
             .unwrap()
             .instances
-            .insert(guild_id, channel_id);
+            .insert(guild_id, channel_ids);
+    }
+
+    /// Returns `false` if the bot is not in a voice channel of the guild.
+    pub fn link_channel(&self, guild_id: GuildId, channel_id: ChannelId) -> bool {
+        self.data
+            .write()
+            .unwrap()
+            .instances
+            .get_mut(&guild_id)
+            .map(|c| c.insert(channel_id))
+            .is_some()
+    }
+
+    /// Returns `false` if the channel has not been linked.
+    pub fn unlink_channel(&self, guild_id: GuildId, channel_id: ChannelId) -> bool {
+        self.data
+            .write()
+            .unwrap()
+            .instances
+            .get_mut(&guild_id)
+            .is_some_and(|c| c.remove(&channel_id))
     }
 
     pub fn destroy_instance(&self, guild_id: GuildId) {
         self.data.write().unwrap().instances.remove(&guild_id);
     }
 
-    pub fn get_sozai_url(&self, key: &str) -> Option<String> {
-        self.data
+    /// Cancels the task previously waiting to leave the guild, if any, in favour of `task`.
+    pub fn replace_leave(&self, guild_id: GuildId, task: AbortHandle) {
+        if let Some(previous) = self.data.write().unwrap().leaves.insert(guild_id, task) {
+            previous.abort();
+        }
+    }
+
+    pub async fn get_utterance(&self, message_id: MessageId) -> Option<Utterance> {
+        self.utterances.get(&message_id).await
+    }
+
+    pub async fn store_utterance(&self, message_id: MessageId, utterance: Utterance) {
+        self.utterances.insert(message_id, utterance).await;
+    }
+
+    pub fn get_sozai(&self, key: &str) -> Option<Arc<sozai::Asset>> {
+        self.data.read().unwrap().sozai.get(key)
+    }
+
+    pub fn sozai_assets(&self) -> Vec<Arc<sozai::Asset>> {
+        self.data.read().unwrap().sozai.assets()
+    }
+
+    /// Reads the sozai index again. The current one is kept if it fails.
+    pub async fn refresh_sozai(&self, source: &sozai::Source) -> anyhow::Result<sozai::Changes> {
+        // The lock is not held while reading, so that sozai are still played meanwhile.
+        let etag = self
+            .data
             .read()
             .unwrap()
-            .sozai_map
-            .get(key)
-            .map(std::borrow::ToOwned::to_owned)
-    }
+            .sozai
+            .etag()
+            .map(ToOwned::to_owned);
+
+        let Some(index) = sozai::Index::fetch(source, etag.as_deref()).await? else {
+            return Ok(sozai::Changes::default());
+        };
 
-    pub async fn init_sozai_map(&self, index_url: &str) -> anyhow::Result<()> {
-        sozai::init(&mut self.data.write().unwrap().sozai_map, index_url).await?;
-        Ok(())
+        let mut data = self.data.write().unwrap();
+        let changes = data.sozai.changes(&index);
+        data.sozai = index;
+
+        Ok(changes)
     }
 }
 
@@ -164,25 +372,127 @@ struct EmojiStructure {
 }
 
 pub struct EmojiDB {
-    data: Arc<HashMap<String, String>>,
+    /// Keyed without variation selectors and skin tones.
+    data: HashMap<String, String>,
 }
 
+const ZWJ: char = '\u{200D}';
+const KEYCAP: char = '\u{20E3}';
+
 impl EmojiDB {
     fn new() -> Result<Self, std::io::Error> {
+        Ok(Self {
+            data: Self::load_dictionary(),
+        })
+    }
+
+    /// Maps every emoji to its name.
+    pub fn load_dictionary() -> HashMap<String, String> {
         let json: HashMap<String, EmojiStructure> =
             serde_json::from_str(include_str!("../assets/emoji_ja.json"))
                 .expect("Emoji DB is corrupted");
 
-        let data = Arc::new(
-            json.iter()
-                .map(|(key, value)| (key.clone(), value.short_name.clone()))
-                .collect(),
-        );
+        json.into_iter()
+            .map(|(key, value)| (normalize_emoji(&key), value.short_name))
+            .collect()
+    }
 
-        Ok(Self { data })
+    /// Replaces every emoji with its name.
+    /// Each grapheme cluster is read as a whole, so that a ZWJ sequence or a flag is not read as its parts.
+    pub fn replace_all(&self, text: &str) -> String {
+        text.graphemes(true)
+            .map(|g| {
+                if g.is_ascii() {
+                    Cow::Borrowed(g)
+                } else {
+                    self.read(g).map_or(Cow::Borrowed(g), Cow::Owned)
+                }
+            })
+            .collect()
     }
 
-    pub fn get_dictionary(&self) -> Arc<HashMap<String, String>> {
-        self.data.clone()
+    fn read(&self, grapheme: &str) -> Option<String> {
+        // Keycaps are read as the key.
+        if grapheme.ends_with(KEYCAP) {
+            return grapheme.chars().next().map(String::from);
+        }
+
+        let key = normalize_emoji(grapheme);
+
+        if let Some(name) = self.data.get(&key) {
+            return Some(name.clone());
+        }
+
+        if key.contains(ZWJ) {
+            return self.read_sequence(&key);
+        }
+
+        // Anything else missing from the dictionary is read as its parts.
+        let names: Vec<&str> = key
+            .char_indices()
+            .filter_map(|(i, c)| self.data.get(&key[i..i + c.len_utf8()]))
+            .map(String::as_str)
+            .collect();
+
+        (!names.is_empty()).then(|| names.join(" "))
+    }
+
+    /// Reads a ZWJ sequence missing from the dictionary as a single emoji, never as a list of its parts.
+    /// Families and couples are read as the emoji for them, anything else as its longest known prefix,
+    /// e.g. the base emoji without a gender sign or hair style.
+    fn read_sequence(&self, key: &str) -> Option<String> {
+        const PEOPLE: &[&str] = &[
+            "\u{1F468}", // man
+            "\u{1F469}", // woman
+            "\u{1F9D1}", // person
+            "\u{1F466}", // boy
+            "\u{1F467}", // girl
+            "\u{1F9D2}", // child
+            "\u{1F476}", // baby
+        ];
+        const HEART: &str = "\u{2764}";
+        const KISS: &str = "\u{1F48B}";
+
+        let parts: Vec<&str> = key.split(ZWJ).collect();
+        let is_person = |p: &&str| PEOPLE.contains(p);
+
+        let group = if parts.iter().all(is_person) {
+            Some("\u{1F46A}") // family
+        } else if parts.contains(&HEART) {
+            let mut rest = parts.iter().filter(|p| !matches!(**p, HEART | KISS));
+
+            rest.all(is_person).then(|| {
+                if parts.contains(&KISS) {
+                    "\u{1F48F}" // kiss
+                } else {
+                    "\u{1F491}" // couple with heart
+                }
+            })
+        } else {
+            None
+        };
+
+        if let Some(name) = group.and_then(|g| self.data.get(g)) {
+            return Some(name.clone());
+        }
+
+        (1..parts.len())
+            .rev()
+            .find_map(|n| self.data.get(&parts[..n].join(&ZWJ.to_string())))
+            .cloned()
     }
 }
+
+/// Strips variation selectors and skin tone modifiers, which do not change the name of an emoji.
+fn normalize_emoji(emoji: &str) -> String {
+    emoji
+        .chars()
+        .filter(|c| !matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}'))
+        .collect()
+}
+
+/// Katakana readings of common English words, keyed in lowercase.
+pub static ENGLISH_DB: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../assets/english_kana.json"))
+        .expect("English DB is corrupted")
+});
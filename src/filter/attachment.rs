@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+
+use serenity::model::channel::{Attachment as MessageAttachment, Message};
+
+use super::{FilterStage, StageContext};
+use crate::settings::AttachmentNotification;
+
+/// Notifies attachments, stickers, embeds and polls, which have no text to be read.
+pub struct Attachment;
+
+impl FilterStage for Attachment {
+    fn name(&self) -> &'static str {
+        "attachment"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        Some(
+            append_attachment_notification(
+                &text,
+                &Counts::of(ctx.message),
+                &ctx.settings.attachment_notification,
+            )
+            .into_owned(),
+        )
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    image: usize,
+    video: usize,
+    audio: usize,
+    file: usize,
+    sticker: usize,
+    embed: usize,
+    poll: usize,
+}
+
+impl Counts {
+    fn of(message: &Message) -> Self {
+        let mut counts = Self {
+            sticker: message.sticker_items.len(),
+            // Link previews are generated from the text, which is read anyway.
+            embed: message
+                .embeds
+                .iter()
+                .filter(|e| e.kind.as_deref() == Some("rich"))
+                .count(),
+            poll: usize::from(message.poll.is_some()),
+            ..Self::default()
+        };
+
+        for attachment in &message.attachments {
+            *counts.of_attachment(attachment) += 1;
+        }
+
+        counts
+    }
+
+    fn of_attachment(&mut self, attachment: &MessageAttachment) -> &mut usize {
+        let content_type = attachment.content_type.as_deref().unwrap_or_default();
+
+        match content_type.split('/').next() {
+            Some("image") => &mut self.image,
+            Some("video") => &mut self.video,
+            Some("audio") => &mut self.audio,
+            // Attachment::dimensions: If this attachment is an image, then a tuple of the width and height in pixels is returned.
+            _ if content_type.is_empty() && attachment.dimensions().is_some() => &mut self.image,
+            _ => &mut self.file,
+        }
+    }
+}
+
+fn append_attachment_notification<'a>(
+    body: &'a str,
+    counts: &Counts,
+    wording: &AttachmentNotification,
+) -> Cow<'a, str> {
+    let items: Vec<_> = [
+        (counts.image, &wording.image),
+        (counts.video, &wording.video),
+        (counts.audio, &wording.audio),
+        (counts.file, &wording.file),
+        (counts.sticker, &wording.sticker),
+        (counts.embed, &wording.embed),
+        (counts.poll, &wording.poll),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .collect();
+
+    let summary = match items.as_slice() {
+        [] => return body.into(),
+        [(1, w)] => w.one.clone(),
+        _ => items
+            .iter()
+            .map(|(count, w)| w.many.replace("{count}", &count.to_string()))
+            .collect::<Vec<_>>()
+            .join(&wording.separator),
+    };
+
+    let mut ret = body.to_string();
+
+    if body.is_empty() {
+        ret.push_str(&wording.sent.replace("{attachments}", &summary));
+    } else {
+        ret.push('。');
+        ret.push_str(&wording.attached.replace("{attachments}", &summary));
+    }
+
+    ret.into()
+}
+
+#[test]
+fn attachment_unit_test() {
+    let wording = AttachmentNotification::default();
+    let images = |image| Counts {
+        image,
+        ..Counts::default()
+    };
+
+    assert_eq!(append_attachment_notification("", &images(0), &wording), "");
+    assert_eq!(
+        append_attachment_notification("", &images(1), &wording),
+        "画像が送信されました"
+    );
+    assert_eq!(
+        append_attachment_notification("", &images(4), &wording),
+        "画像4枚が送信されました"
+    );
+    assert_eq!(
+        append_attachment_notification("あ", &images(0), &wording),
+        "あ"
+    );
+    assert_eq!(
+        append_attachment_notification("あ", &images(1), &wording),
+        "あ。画像添付"
+    );
+    assert_eq!(
+        append_attachment_notification("あ", &images(4), &wording),
+        "あ。画像4枚添付"
+    );
+
+    let counts = Counts {
+        image: 2,
+        video: 1,
+        file: 1,
+        ..Counts::default()
+    };
+    assert_eq!(
+        append_attachment_notification("", &counts, &wording),
+        "画像2枚と動画1件とファイル1件が送信されました"
+    );
+
+    let counts = Counts {
+        sticker: 1,
+        ..Counts::default()
+    };
+    assert_eq!(
+        append_attachment_notification("", &counts, &wording),
+        "スタンプが送信されました"
+    );
+
+    let counts = Counts {
+        poll: 1,
+        embed: 2,
+        ..Counts::default()
+    };
+    assert_eq!(
+        append_attachment_notification("見て", &counts, &wording),
+        "見て。埋め込み2件と投票1件添付"
+    );
+}
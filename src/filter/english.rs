@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{FilterStage, StageContext};
+use crate::db::ENGLISH_DB;
+use crate::replacer::Replacer;
+
+static WORD_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z]+(?:'[A-Za-z]+)*").unwrap());
+
+/// Endings which are not read the way they are spelt.
+const SUFFIXES: &[(&str, &str)] = &[
+    ("tion", "ション"),
+    ("sion", "ジョン"),
+    ("ture", "チャー"),
+    ("ble", "ブル"),
+    ("ple", "プル"),
+    ("tle", "トル"),
+    ("cle", "クル"),
+];
+
+/// Spellings which are read as a single vowel, longest first.
+const VOWEL_GROUPS: &[&str] = &[
+    "ee", "ea", "ie", "oo", "ou", "ow", "ai", "ay", "ei", "ey", "oa", "au", "aw", "oi", "oy", "ew",
+    "ue",
+];
+
+/// Spellings which are read as a single consonant, longest first.
+const CONSONANT_GROUPS: &[(&str, &str)] = &[("tch", "ch"), ("ch", "ch"), ("sh", "sh"), ("th", "s")];
+
+/// Consonants before which a short vowel is cut off with `ッ`.
+const STOPS: &[&str] = &["k", "t", "p", "d", "g", "ch"];
+
+/// Reads English words in katakana, since the engine spells out every word it does not know.
+/// Words missing from the bundled dictionary are read by the rules below.
+pub struct English;
+
+impl FilterStage for English {
+    fn name(&self) -> &'static str {
+        "english"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        if !ctx.settings.read_english {
+            return Some(text);
+        }
+
+        Some(replace_outside_words(&text, ctx.dictionary, &ENGLISH_DB))
+    }
+}
+
+/// Words in the guild's dictionary are left for the dictionary stage, which runs next,
+/// so that neither they nor the readings registered for them are read as English.
+fn replace_outside_words(
+    mes: &str,
+    words: &Replacer,
+    dictionary: &HashMap<String, String>,
+) -> String {
+    words.map_unmatched(mes, |part| replace_english(part, dictionary))
+}
+
+fn replace_english(mes: &str, dictionary: &HashMap<String, String>) -> String {
+    WORD_REGEX
+        .replace_all(mes, |caps: &regex::Captures| {
+            let whole = caps.get(0).unwrap();
+            // `mp3` or `5min` is not a word.
+            let next_to_digit = mes[..whole.start()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_digit())
+                || mes[whole.end()..]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_digit());
+
+            (!next_to_digit)
+                .then(|| read_word(whole.as_str(), dictionary))
+                .flatten()
+                .unwrap_or_else(|| whole.as_str().to_string())
+        })
+        .into_owned()
+}
+
+fn read_word(word: &str, dictionary: &HashMap<String, String>) -> Option<String> {
+    let lower = word.to_ascii_lowercase();
+
+    if let Some(reading) = dictionary.get(&lower) {
+        return Some(reading.clone());
+    }
+
+    // Single letters and acronyms like `URL` are best spelt out, which the engine does anyway.
+    if word.len() == 1 || word.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let stem = lower
+        .strip_suffix("'s")
+        .or_else(|| lower.strip_suffix('s'))
+        .and_then(|stem| dictionary.get(stem));
+
+    if let Some(stem) = stem {
+        return Some(pluralize(stem));
+    }
+
+    Some(transliterate(&lower.replace('\'', "")))
+}
+
+fn pluralize(reading: &str) -> String {
+    if let Some(stem) = reading.strip_suffix('ト') {
+        format!("{stem}ツ")
+    } else if let Some(stem) = reading.strip_suffix('ド') {
+        format!("{stem}ズ")
+    } else if reading.ends_with(['ク', 'プ', 'フ']) {
+        format!("{reading}ス")
+    } else {
+        format!("{reading}ズ")
+    }
+}
+
+fn transliterate(word: &str) -> String {
+    let suffix = SUFFIXES.iter().find_map(|(suffix, reading)| {
+        word.strip_suffix(suffix)
+            .filter(|stem| !stem.is_empty())
+            .map(|stem| (stem, reading))
+    });
+
+    match suffix {
+        Some((stem, reading)) => format!("{}{reading}", render(&tokenize(stem), false)),
+        None => render(&tokenize(word), true),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Consonant { sound: &'static str, doubled: bool },
+    Vowel(&'static str),
+}
+
+/// Splits a lowercase word into vowels and consonants as they sound.
+fn tokenize(word: &str) -> Vec<Token> {
+    // `box` has a short vowel cut off, while `taxi` does not.
+    let word = match word.strip_suffix('x') {
+        Some(stem) => format!("{stem}kks"),
+        None => word.to_string(),
+    };
+    // The input is in lowercase, so `I` stands for `igh` in `night`.
+    let word = word
+        .replace("igh", "I")
+        .replace("gh", "")
+        .replace("ph", "f")
+        .replace("ck", "kk")
+        .replace("qu", "kw")
+        .replace('x', "ks")
+        .replace("wh", "w");
+    let word = word
+        .strip_prefix("wr")
+        .or_else(|| word.strip_prefix("kn"))
+        .map_or(word.clone(), |rest| format!("{}{rest}", &word[1..2]));
+
+    let bytes = word.as_bytes();
+    let is_vowel = |i: usize| bytes.get(i).is_some_and(|c| b"aeiouI".contains(c));
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &word[i..];
+
+        // `ow` in `power` is `o` followed by `we`.
+        if let Some(group) = VOWEL_GROUPS
+            .iter()
+            .find(|g| rest.starts_with(**g) && !(g.ends_with('w') && is_vowel(i + 2)))
+        {
+            tokens.push(Token::Vowel(group));
+            i += 2;
+            continue;
+        }
+
+        if let Some(vowel) = single_vowel(bytes[i]).filter(|_| bytes[i] != b'y' || !is_vowel(i + 1))
+        {
+            tokens.push(Token::Vowel(vowel));
+            i += 1;
+            continue;
+        }
+
+        if let Some((spelling, sound)) = CONSONANT_GROUPS.iter().find(|(g, _)| rest.starts_with(g))
+        {
+            tokens.push(Token::Consonant {
+                sound,
+                doubled: false,
+            });
+            i += spelling.len();
+            continue;
+        }
+
+        if rest.starts_with("ng") && !is_vowel(i + 2) {
+            tokens.push(Token::Consonant {
+                sound: "ng",
+                doubled: false,
+            });
+            i += 2;
+            continue;
+        }
+
+        let doubled = bytes.get(i + 1) == Some(&bytes[i]);
+        tokens.push(Token::Consonant {
+            sound: consonant(rest),
+            doubled,
+        });
+        i += if doubled { 2 } else { 1 };
+    }
+
+    tokens
+}
+
+fn single_vowel(c: u8) -> Option<&'static str> {
+    Some(match c {
+        b'a' => "a",
+        b'e' => "e",
+        b'i' => "i",
+        b'o' => "o",
+        b'u' => "u",
+        b'y' => "y",
+        b'I' => "igh",
+        _ => return None,
+    })
+}
+
+fn consonant(rest: &str) -> &'static str {
+    let soft = rest[1..].starts_with(['e', 'i', 'y']);
+
+    match rest.as_bytes()[0] {
+        b'c' if soft => "s",
+        b'c' | b'k' | b'q' => "k",
+        // `g` is hard in `get` and `give`, but not in `page` or `energy`.
+        b'g' if rest == "ge" || rest[1..].starts_with('y') => "j",
+        b'g' => "g",
+        b's' => "s",
+        b'z' => "z",
+        b't' => "t",
+        b'd' => "d",
+        b'n' => "n",
+        b'h' => "h",
+        b'f' => "f",
+        b'b' => "b",
+        b'p' => "p",
+        b'm' => "m",
+        b'y' => "y",
+        b'r' => "r",
+        b'l' => "l",
+        b'w' => "w",
+        b'v' => "v",
+        b'j' => "j",
+        _ => "",
+    }
+}
+
+fn row(consonant: &str) -> [&'static str; 5] {
+    match consonant {
+        "k" => ["カ", "キ", "ク", "ケ", "コ"],
+        "g" => ["ガ", "ギ", "グ", "ゲ", "ゴ"],
+        "s" => ["サ", "シ", "ス", "セ", "ソ"],
+        "z" => ["ザ", "ジ", "ズ", "ゼ", "ゾ"],
+        "t" => ["タ", "ティ", "トゥ", "テ", "ト"],
+        "d" => ["ダ", "ディ", "ドゥ", "デ", "ド"],
+        "n" | "ng" => ["ナ", "ニ", "ヌ", "ネ", "ノ"],
+        "h" => ["ハ", "ヒ", "フ", "ヘ", "ホ"],
+        "f" => ["ファ", "フィ", "フ", "フェ", "フォ"],
+        "b" => ["バ", "ビ", "ブ", "ベ", "ボ"],
+        "p" => ["パ", "ピ", "プ", "ペ", "ポ"],
+        "m" => ["マ", "ミ", "ム", "メ", "モ"],
+        "y" => ["ヤ", "イ", "ユ", "イェ", "ヨ"],
+        "r" | "l" => ["ラ", "リ", "ル", "レ", "ロ"],
+        "w" => ["ワ", "ウィ", "ウ", "ウェ", "ウォ"],
+        "v" => ["ヴァ", "ヴィ", "ヴ", "ヴェ", "ヴォ"],
+        "j" => ["ジャ", "ジ", "ジュ", "ジェ", "ジョ"],
+        "ch" => ["チャ", "チ", "チュ", "チェ", "チョ"],
+        "sh" => ["シャ", "シ", "シュ", "シェ", "ショ"],
+        _ => ["ア", "イ", "ウ", "エ", "オ"],
+    }
+}
+
+/// How a vowel is read: a column of the kana table followed by a tail, or `ユー`.
+enum Sound {
+    Plain(usize, &'static str),
+    Yu,
+}
+
+fn render(tokens: &[Token], at_end: bool) -> String {
+    let mut tokens = tokens.to_vec();
+    let vowels = tokens
+        .iter()
+        .filter(|t| matches!(t, Token::Vowel(_)))
+        .count();
+    let mut magic = None;
+
+    // The final `e` of `time` is silent, and makes the vowel before it long.
+    if vowels > 1
+        && tokens.last() == Some(&Token::Vowel("e"))
+        && matches!(tokens.iter().nth_back(1), Some(Token::Consonant { .. }))
+    {
+        tokens.pop();
+
+        if let [.., Token::Vowel(v), Token::Consonant { doubled: false, .. }] = tokens[..] {
+            magic = (v.len() == 1).then_some(tokens.len() - 2);
+        }
+    }
+
+    let mut ret = String::new();
+    let mut k = 0;
+
+    while k < tokens.len() {
+        let (onset, vowel) = match tokens[k] {
+            Token::Consonant { sound, doubled } => {
+                if doubled && STOPS.contains(&sound) {
+                    ret.push('ッ');
+                } else if doubled && matches!(sound, "n" | "m") {
+                    ret.push('ン');
+                }
+
+                if !matches!(tokens.get(k + 1), Some(Token::Vowel(_))) {
+                    // `hit` and `dog`, but not `ticket` or `project`.
+                    let cut = at_end
+                        && vowels == 1
+                        && k + 1 == tokens.len()
+                        && !doubled
+                        && STOPS.contains(&sound)
+                        && matches!(k.checked_sub(1).map(|j| tokens[j]), Some(Token::Vowel(v)) if v.len() == 1);
+
+                    if cut {
+                        ret.push('ッ');
+                    }
+
+                    ret.push_str(coda(sound, tokens.get(k + 1)));
+                    k += 1;
+                    continue;
+                }
+
+                (sound, k + 1)
+            }
+            Token::Vowel(_) => ("", k),
+        };
+
+        let (sound, consumed) = vowel_sound(&tokens, vowel, magic == Some(vowel), vowels);
+
+        match sound {
+            Sound::Plain(column, tail) => {
+                ret.push_str(row(onset)[column]);
+                ret.push_str(tail);
+            }
+            Sound::Yu if onset.is_empty() => ret.push_str("ユー"),
+            Sound::Yu if matches!(onset, "k" | "g" | "n" | "h" | "b" | "p" | "m") => {
+                ret.push_str(row(onset)[1]);
+                ret.push_str("ュー");
+            }
+            Sound::Yu => {
+                ret.push_str(row(onset)[2]);
+                ret.push('ー');
+            }
+        }
+
+        k = vowel + consumed;
+    }
+
+    ret
+}
+
+/// Returns how the vowel at `k` is read, and how many tokens it takes up with a following `r`.
+fn vowel_sound(tokens: &[Token], k: usize, magic: bool, vowels: usize) -> (Sound, usize) {
+    let Token::Vowel(vowel) = tokens[k] else {
+        unreachable!("not a vowel");
+    };
+    let next = tokens.get(k + 1);
+    let followed_by_vowel = matches!(tokens.get(k + 2), Some(Token::Vowel(_)));
+    let closed = match next {
+        Some(Token::Consonant { doubled: true, .. }) => true,
+        Some(Token::Consonant { .. }) => !followed_by_vowel,
+        _ => false,
+    };
+    let last = k + 1 == tokens.len();
+
+    // The vowel before the silent `e` of `time` is read as it is named.
+    let vowel = match vowel {
+        "a" if magic => "ai",
+        "e" if magic => "ee",
+        "i" if magic => "igh",
+        "o" if magic => "oa",
+        "u" if magic => "ew",
+        vowel => vowel,
+    };
+
+    // `r` after a vowel is not read, but makes it long like in `car` and `first`.
+    if matches!(
+        next,
+        Some(Token::Consonant {
+            sound: "r",
+            doubled: false
+        })
+    ) && !followed_by_vowel
+    {
+        let sound = match vowel {
+            "igh" => Sound::Plain(0, "イア"),
+            "oa" if magic => Sound::Plain(4, "ア"),
+            "o" | "oa" | "au" | "aw" => Sound::Plain(4, "ー"),
+            "a" | "e" | "i" | "u" | "y" => Sound::Plain(0, "ー"),
+            "ee" | "ea" | "ie" => Sound::Plain(1, "ア"),
+            "ai" | "ay" | "ei" | "ey" => Sound::Plain(3, "ア"),
+            _ => Sound::Plain(2, "アー"),
+        };
+
+        return (sound, 2);
+    }
+
+    let sound = match vowel {
+        "igh" => Sound::Plain(0, "イ"),
+        "u" if !closed => Sound::Yu,
+        "a" | "u" => Sound::Plain(0, ""),
+        "e" => Sound::Plain(3, ""),
+        "o" => Sound::Plain(4, ""),
+        "y" if last && vowels == 1 => Sound::Plain(0, "イ"),
+        "y" if last => Sound::Plain(1, "ー"),
+        "i" | "y" => Sound::Plain(1, ""),
+        "ee" | "ea" | "ie" => Sound::Plain(1, "ー"),
+        "oo" => Sound::Plain(2, "ー"),
+        "ou" | "ow" => Sound::Plain(0, "ウ"),
+        "ai" | "ay" | "ei" | "ey" => Sound::Plain(3, "イ"),
+        "oa" | "au" | "aw" => Sound::Plain(4, "ー"),
+        "oi" | "oy" => Sound::Plain(4, "イ"),
+        _ => Sound::Yu,
+    };
+
+    (sound, 1)
+}
+
+/// Reads a consonant without a vowel after it.
+fn coda(consonant: &str, next: Option<&Token>) -> &'static str {
+    match consonant {
+        "t" => "ト",
+        "d" => "ド",
+        "ch" => "チ",
+        "sh" => "シュ",
+        "j" => "ジ",
+        "n" => "ン",
+        "ng" => "ング",
+        "r" | "l" => "ル",
+        "m" if matches!(
+            next,
+            Some(Token::Consonant {
+                sound: "b" | "p",
+                ..
+            })
+        ) =>
+        {
+            "ン"
+        }
+        _ => row(consonant)[2],
+    }
+}
+
+#[test]
+fn english_unit_test() {
+    let dictionary = HashMap::from([
+        ("hello".to_string(), "ハロー".to_string()),
+        ("cat".to_string(), "キャット".to_string()),
+        ("book".to_string(), "ブック".to_string()),
+        ("friend".to_string(), "フレンド".to_string()),
+        ("game".to_string(), "ゲーム".to_string()),
+    ]);
+    let replace = |mes| replace_english(mes, &dictionary);
+
+    assert_eq!(replace("こんにちは"), "こんにちは");
+    assert_eq!(replace("Hello zoom"), "ハロー ズーム");
+    assert_eq!(replace("HELLOです"), "ハローです");
+    assert_eq!(replace("URLを貼って"), "URLを貼って");
+    assert_eq!(replace("I think"), "I シンク");
+    assert_eq!(replace("mp3とmp4"), "mp3とmp4");
+    assert_eq!(replace("cats"), "キャッツ");
+    assert_eq!(replace("books"), "ブックス");
+    assert_eq!(replace("friends"), "フレンズ");
+    assert_eq!(replace("games"), "ゲームズ");
+    assert_eq!(replace("cat's"), "キャッツ");
+
+    let words = Replacer::new(&HashMap::from([
+        ("VC".to_string(), "VC部屋".to_string()),
+        ("ゲーム".to_string(), "Nice game".to_string()),
+    ]));
+    let read = |mes| words.replace_all(&replace_outside_words(mes, &words, &dictionary));
+    assert_eq!(read("VCでcat"), "VC部屋でキャット");
+    assert_eq!(read("ゲームとcat"), "Nice gameとキャット");
+
+    let cases = [
+        ("zoom", "ズーム"),
+        ("slack", "スラック"),
+        ("twitch", "トウィッチ"),
+        ("tweet", "トウィート"),
+        ("bake", "ベイク"),
+        ("smile", "スマイル"),
+        ("number", "ナンバー"),
+        ("sister", "シスター"),
+        ("yoga", "ヨガ"),
+        ("night", "ナイト"),
+        ("highway", "ハイウェイ"),
+        ("happy", "ハッピー"),
+        ("fly", "フライ"),
+        ("stop", "ストップ"),
+        ("running", "ランニング"),
+        ("cute", "キュート"),
+        ("age", "エイジ"),
+        ("station", "スタション"),
+        ("care", "ケア"),
+        ("fire", "ファイア"),
+        ("store", "ストア"),
+        ("car", "カー"),
+        ("fox", "フォックス"),
+        ("queen", "クウィーン"),
+    ];
+
+    for (word, expected) in cases {
+        assert_eq!(transliterate(word), expected, "{word}");
+    }
+
+    for word in [
+        "rhythm", "strength", "aaa", "q", "e", "y", "ye", "eye", "wrkn", "ngng",
+    ] {
+        assert!(!transliterate(word).is_empty(), "{word}");
+    }
+}
@@ -0,0 +1,137 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Mentions, custom emoji, timestamps, URIs and code say nothing about the language.
+static NOISE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)```.*?```|`[^`]*`|<[^<>]*>|:\w+:|[A-Za-z][A-Za-z0-9+\-.]*://\S+").unwrap()
+});
+
+/// Messages with fewer letters than this are taken as Japanese, so that `ok` or `gg` is read as usual.
+const MIN_LETTERS: usize = 8;
+
+/// Stages which read things in Japanese, skipped for a message read in another language.
+pub const JAPANESE_STAGES: &[&str] =
+    &["timestamp", "number", "repetition", "dictionary", "english"];
+
+/// Guesses the language of a message from the scripts it is written in.
+/// Returns `None` for Japanese, or if there is too little to tell.
+///
+/// Kanji alone cannot tell Chinese from Japanese, so they are taken as Japanese,
+/// and every message in the Latin script is taken as English.
+pub fn detect(mes: &str) -> Option<&'static str> {
+    let mes = NOISE_REGEX.replace_all(mes, " ");
+    let mut counts = [0usize; Script::COUNT];
+
+    let mut previous = None;
+
+    for c in mes.chars() {
+        let Some(script) = Script::of(c) else {
+            previous = Some(c);
+            continue;
+        };
+
+        // A run of one letter such as `wwww` is laughter rather than a word, so only its first letter counts.
+        if !(script == Script::Latin && previous == Some(c)) {
+            counts[script as usize] += 1;
+        }
+
+        previous = Some(c);
+    }
+
+    let letters: usize = counts.iter().sum();
+    let (script, count) = Script::ALL
+        .into_iter()
+        .map(|s| (s, counts[s as usize]))
+        .max_by_key(|(_, count)| *count)?;
+
+    // Any kana makes it Japanese, even with English words in it.
+    if counts[Script::Kana as usize] > 0 || letters < MIN_LETTERS || count * 2 <= letters {
+        return None;
+    }
+
+    script.language()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Kana,
+    Han,
+    Latin,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Devanagari,
+    Thai,
+}
+
+impl Script {
+    const ALL: [Self; 9] = [
+        Self::Kana,
+        Self::Han,
+        Self::Latin,
+        Self::Hangul,
+        Self::Cyrillic,
+        Self::Greek,
+        Self::Arabic,
+        Self::Devanagari,
+        Self::Thai,
+    ];
+    const COUNT: usize = Self::ALL.len();
+
+    fn of(c: char) -> Option<Self> {
+        Some(match c {
+            '\u{3041}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+                Self::Kana
+            }
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => Self::Han,
+            'A'..='Z' | 'a'..='z' | '\u{C0}'..='\u{24F}' => Self::Latin,
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => {
+                Self::Hangul
+            }
+            '\u{400}'..='\u{4FF}' => Self::Cyrillic,
+            '\u{370}'..='\u{3FF}' => Self::Greek,
+            '\u{600}'..='\u{6FF}' => Self::Arabic,
+            '\u{900}'..='\u{97F}' => Self::Devanagari,
+            '\u{E00}'..='\u{E7F}' => Self::Thai,
+            _ => return None,
+        })
+    }
+
+    fn language(self) -> Option<&'static str> {
+        match self {
+            Self::Kana | Self::Han => None,
+            Self::Latin => Some("en"),
+            Self::Hangul => Some("ko"),
+            Self::Cyrillic => Some("ru"),
+            Self::Greek => Some("el"),
+            Self::Arabic => Some("ar"),
+            Self::Devanagari => Some("hi"),
+            Self::Thai => Some("th"),
+        }
+    }
+}
+
+#[test]
+fn language_unit_test() {
+    assert_eq!(detect(""), None);
+    assert_eq!(detect("こんにちは"), None);
+    assert_eq!(detect("明日十時東京駅集合厳守"), None);
+    assert_eq!(detect("ok"), None);
+    assert_eq!(detect("www"), None);
+    assert_eq!(detect("wwwwwwwwww"), None);
+    assert_eq!(detect("草wwwwwwwwww"), None);
+    assert_eq!(detect("ｗｗｗｗｗｗｗｗｗｗ"), None);
+    assert_eq!(detect("Good morning everyone"), Some("en"));
+    assert_eq!(detect("Discordのbotを作った"), None);
+    assert_eq!(detect("I made a bot for Discord"), Some("en"));
+    assert_eq!(detect("안녕하세요 반갑습니다"), Some("ko"));
+    assert_eq!(detect("Привет, как дела?"), Some("ru"));
+    assert_eq!(detect("Ça va très bien, merci"), Some("en"));
+    assert_eq!(
+        detect("<@123456789> <:smile:123> https://example.com/path hi"),
+        None
+    );
+    assert_eq!(detect("```rust\nfn main() {}\n``` 動いた"), None);
+    assert_eq!(detect("see https://example.com/a/b/c/d/e/f/g"), None);
+}
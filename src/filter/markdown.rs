@@ -0,0 +1,134 @@
+use once_cell::sync::Lazy;
+use regex::{NoExpand, Regex};
+
+use super::{FilterStage, StageContext};
+
+static INLINE_CODE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"``[^\n]+?``|`[^`\n]+`").unwrap());
+static SPOILER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)\|\|.+?\|\|").unwrap());
+static HEADING_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:#{1,3}|-#) +").unwrap());
+static LIST_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*[-*] +").unwrap());
+static EMPHASIS_REGEXES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        r"\*\*(.+?)\*\*",
+        r"__(.+?)__",
+        r"~~(.+?)~~",
+        r"\b_([^_\s](?:.*?[^_\s])?)_\b",
+    ]
+    .iter()
+    .map(|r| Regex::new(r).unwrap())
+    .collect()
+});
+static ITALIC_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\*([^*\s](?:.*?[^*\s])??)\*").unwrap());
+
+/// Strips the formatting characters of Discord markdown.
+/// Code blocks are left to [`super::stages::Codeblock`], which must run before this stage.
+pub struct Markdown;
+
+impl FilterStage for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        let settings = ctx.settings;
+
+        Some(strip_markdown(
+            &text,
+            &settings.code_replacement,
+            &settings.spoiler_replacement,
+            settings.read_quotes,
+        ))
+    }
+}
+
+fn strip_markdown(
+    mes: &str,
+    code_replacement: &str,
+    spoiler_replacement: &str,
+    read_quotes: bool,
+) -> String {
+    let s = INLINE_CODE_REGEX.replace_all(mes, NoExpand(code_replacement));
+    let s = SPOILER_REGEX.replace_all(&s, NoExpand(spoiler_replacement));
+
+    let mut lines = vec![];
+    let mut in_multiline_quote = false;
+
+    for line in s.lines() {
+        let (line, quote) = if in_multiline_quote {
+            (line, true)
+        } else if let Some(rest) = line.strip_prefix(">>> ") {
+            in_multiline_quote = true;
+            (rest, true)
+        } else if let Some(rest) = line.strip_prefix("> ") {
+            (rest, true)
+        } else {
+            (line, false)
+        };
+
+        if quote && !read_quotes {
+            continue;
+        }
+
+        let line = HEADING_REGEX.replace(line, "");
+        let line = LIST_REGEX.replace(&line, "");
+
+        lines.push(line.into_owned());
+    }
+
+    let mut s = lines.join("\n");
+
+    for regex in EMPHASIS_REGEXES.iter() {
+        s = regex.replace_all(&s, "${1}").into_owned();
+    }
+
+    // Asterisks between numbers are multiplication, e.g. `2*3*4`.
+    ITALIC_REGEX
+        .replace_all(&s, |caps: &regex::Captures| {
+            let whole = caps.get(0).unwrap();
+            let before = s[..whole.start()].chars().next_back();
+            let after = s[whole.end()..].chars().next();
+
+            if before.into_iter().chain(after).any(|c| c.is_ascii_digit()) {
+                whole.as_str().to_string()
+            } else {
+                caps[1].to_string()
+            }
+        })
+        .into_owned()
+}
+
+#[test]
+fn markdown_unit_test() {
+    let strip = |mes| strip_markdown(mes, "。コード省略。", "伏せ字", true);
+
+    assert_eq!(strip("hello"), "hello");
+    assert_eq!(strip("run `cargo build` now"), "run 。コード省略。 now");
+    assert_eq!(strip("``a ` b``"), "。コード省略。");
+    assert_eq!(strip("犯人は||ヤス||"), "犯人は伏せ字");
+    assert_eq!(strip("||a|| and ||b||"), "伏せ字 and 伏せ字");
+    assert_eq!(strip("# 見出し"), "見出し");
+    assert_eq!(strip("### 見出し"), "見出し");
+    assert_eq!(strip("#hashtag"), "#hashtag");
+    assert_eq!(strip("-# 小さい文字"), "小さい文字");
+    assert_eq!(strip("- one\n- two"), "one\ntwo");
+    assert_eq!(strip("> quoted\nreply"), "quoted\nreply");
+    assert_eq!(strip(">>> all\nquoted"), "all\nquoted");
+    assert_eq!(strip("**bold** and *italic*"), "bold and italic");
+    assert_eq!(
+        strip("__underline__ ~~strike~~ _italic_"),
+        "underline strike italic"
+    );
+    assert_eq!(strip("snake_case_name"), "snake_case_name");
+    assert_eq!(strip("2 * 3 * 4"), "2 * 3 * 4");
+    assert_eq!(strip("2*3*4"), "2*3*4");
+    assert_eq!(strip("*x* 2*3*4"), "x 2*3*4");
+    assert_eq!(strip("これは*強調*です"), "これは強調です");
+    assert_eq!(strip("$1 **$2**"), "$1 $2");
+
+    let skip_quotes = |mes| strip_markdown(mes, "", "", false);
+
+    assert_eq!(skip_quotes("> quoted\nreply"), "reply");
+    assert_eq!(skip_quotes("reply\n>>> all\nquoted"), "reply");
+    assert_eq!(skip_quotes("a > b"), "a > b");
+}
@@ -0,0 +1,209 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serenity::{
+    cache::Cache,
+    http::CacheHttp,
+    model::{
+        channel::{Channel, Message},
+        id::{ChannelId, RoleId, UserId},
+    },
+};
+
+use crate::db::PERSISTENT_DB;
+
+// regex crate's named capture
+#[allow(clippy::invalid_regex)]
+static MENTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"<@!?(?<user>\d+)>",
+        r"|<@&(?<role>\d+)>",
+        r"|<#(?<channel>\d+)>",
+        r"|</(?<command>[^:<>]+):\d+>",
+        r"|\B@(?<everyone>everyone|here)\b",
+    ))
+    .unwrap()
+});
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mention<'a> {
+    User(UserId),
+    Role(RoleId),
+    Channel(ChannelId),
+    Command(&'a str),
+    Everyone,
+    Here,
+}
+
+/// Replaces mentions of users, roles and channels with their names, and reads commands.
+/// Names missing from the cache are fetched, and anything which cannot be resolved is read generically.
+pub async fn resolve_mentions<T>(ctx: T, mes: &Message) -> String
+where
+    T: CacheHttp + AsRef<Cache> + Copy,
+{
+    let content = mes.content.as_str();
+    let mut ret = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for (start, end, mention) in parse(content) {
+        ret.push_str(&content[last..start]);
+        ret.push_str(&read(ctx, mes, mention).await);
+        last = end;
+    }
+
+    ret.push_str(&content[last..]);
+    ret
+}
+
+fn parse(content: &str) -> Vec<(usize, usize, Mention<'_>)> {
+    MENTION_REGEX
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let id = |name| {
+                caps.name(name)?
+                    .as_str()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|id| *id != 0)
+            };
+            let whole = caps.get(0).unwrap();
+
+            let mention = if let Some(id) = id("user") {
+                Mention::User(UserId::new(id))
+            } else if let Some(id) = id("role") {
+                Mention::Role(RoleId::new(id))
+            } else if let Some(id) = id("channel") {
+                Mention::Channel(ChannelId::new(id))
+            } else if let Some(command) = caps.name("command") {
+                Mention::Command(command.as_str())
+            } else if caps.name("everyone")?.as_str() == "everyone" {
+                Mention::Everyone
+            } else {
+                Mention::Here
+            };
+
+            Some((whole.start(), whole.end(), mention))
+        })
+        .collect()
+}
+
+async fn read<T>(ctx: T, mes: &Message, mention: Mention<'_>) -> String
+where
+    T: CacheHttp + AsRef<Cache> + Copy,
+{
+    match mention {
+        Mention::User(id) => format!("。宛、{}。", user_name(ctx, mes, id).await),
+        Mention::Role(id) => format!("。宛、{}。", role_name(ctx, mes, id).await),
+        Mention::Channel(id) => format!("。宛、{}。", channel_name(ctx, mes, id).await),
+        Mention::Everyone => "。宛、全員。".to_string(),
+        Mention::Here => "。宛、ここにいる人。".to_string(),
+        Mention::Command(command) => format!("{command}コマンド"),
+    }
+}
+
+async fn user_name<T>(ctx: T, mes: &Message, id: UserId) -> String
+where
+    T: CacheHttp + Copy,
+{
+    if let Some(reading) = mes
+        .guild_id
+        .and_then(|guild_id| PERSISTENT_DB.get_name_reading(guild_id, id))
+    {
+        return reading;
+    }
+
+    // Looks up the cache first.
+    if let Some(guild_id) = mes.guild_id {
+        if let Ok(member) = guild_id.member(ctx, id).await {
+            return member.display_name().to_string();
+        }
+    }
+
+    if let Some(user) = mes.mentions.iter().find(|u| u.id == id) {
+        return user.global_name.as_ref().unwrap_or(&user.name).clone();
+    }
+
+    match id.to_user(ctx).await {
+        Ok(user) => user.global_name.unwrap_or(user.name),
+        Err(_) => "誰か".to_string(),
+    }
+}
+
+async fn role_name<T>(ctx: T, mes: &Message, id: RoleId) -> String
+where
+    T: CacheHttp + AsRef<Cache>,
+{
+    let Some(guild_id) = mes.guild_id else {
+        return "誰か".to_string();
+    };
+
+    let cached = ctx
+        .as_ref()
+        .guild(guild_id)
+        .and_then(|g| g.roles.get(&id).map(|r| r.name.clone()));
+
+    if let Some(name) = cached {
+        return name;
+    }
+
+    match guild_id.roles(ctx.http()).await {
+        Ok(roles) => roles
+            .get(&id)
+            .map_or_else(|| "誰か".to_string(), |r| r.name.clone()),
+        Err(_) => "誰か".to_string(),
+    }
+}
+
+async fn channel_name<T>(ctx: T, mes: &Message, id: ChannelId) -> String
+where
+    T: CacheHttp + AsRef<Cache>,
+{
+    let cached = mes.guild_id.and_then(|guild_id| {
+        let guild = ctx.as_ref().guild(guild_id)?;
+
+        guild
+            .channels
+            .get(&id)
+            .or_else(|| guild.threads.iter().find(|t| t.id == id))
+            .map(|c| c.name.clone())
+    });
+
+    if let Some(name) = cached {
+        return name;
+    }
+
+    match id.to_channel(ctx).await {
+        Ok(Channel::Guild(channel)) => channel.name,
+        _ => "不明なチャンネル".to_string(),
+    }
+}
+
+#[test]
+fn mention_unit_test() {
+    let mentions = |content| {
+        parse(content)
+            .into_iter()
+            .map(|(_, _, m)| m)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(mentions("hello"), []);
+    assert_eq!(
+        mentions("<@123> <@!456> <@&789> <#12>"),
+        [
+            Mention::User(UserId::new(123)),
+            Mention::User(UserId::new(456)),
+            Mention::Role(RoleId::new(789)),
+            Mention::Channel(ChannelId::new(12)),
+        ]
+    );
+    assert_eq!(
+        mentions("@everyone @here mail@here.com @hereafter"),
+        [Mention::Everyone, Mention::Here]
+    );
+    assert_eq!(
+        mentions("</join:1> </dict add:2>"),
+        [Mention::Command("join"), Mention::Command("dict add")]
+    );
+    assert_eq!(parse("a<@1>b")[0].0..parse("a<@1>b")[0].1, 1..5);
+    assert_eq!(mentions("<@0>"), []);
+}
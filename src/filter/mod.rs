@@ -0,0 +1,483 @@
+mod attachment;
+mod english;
+mod language;
+mod markdown;
+mod mention;
+mod number;
+mod repetition;
+mod stages;
+mod timestamp;
+
+use serenity::{
+    cache::Cache,
+    http::CacheHttp,
+    model::{
+        channel::{Message, MessageType},
+        guild::Guild,
+        user::User,
+    },
+};
+
+use crate::db::{INMEMORY_DB, PERSISTENT_DB};
+use crate::replacer::Replacer;
+use crate::settings::{EditReadMode, ForeignLanguageMode, GuildSettings};
+use crate::tts::Voice;
+
+/// What a stage may look at besides the text.
+pub struct StageContext<'a> {
+    pub message: &'a Message,
+    pub settings: &'a GuildSettings,
+    /// Words registered with `/dict`, replaced by their readings.
+    pub dictionary: &'a Replacer,
+}
+
+/// A step of turning a message into the text to be read.
+pub trait FilterStage: Sync {
+    /// Identifies the stage in the `disabled_stages` setting.
+    fn name(&self) -> &'static str;
+
+    /// Returns `None` to stop the message from being read at all.
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String>;
+}
+
+// `<a:emoji_identifier:123456789>` should be treated as a single emoji and not `<a:emoji_。URI省略。>`,
+// so external_emoji must precede uri.
+// On the other hand, `protocol:host:23` should be treated as a `。URI省略。` and not `protocol23` (:host: replaced by `emoji`),
+// so uri must precede emoji.
+//
+// The order `external_emoji` -> `uri` -> `emoji` rests upon the observation that
+// an external_emoji cannot be a part of an URI (since an URI cannot contain a letter "<", as per RFC3986),
+// and the design decision that we want to treat a string like `<a:crime:1238318711>` as a single `external_emoji` and not
+// `<。URI省略。>` or `<a:。URI省略。>`. I mean, why would anyone enclose a strange URI within a pair of angle brackets?
+//
+// Likewise `<t:1618953630:F>` should be read as a timestamp before uri takes `t:1618953630:F` for an URI,
+// so timestamp precedes them all.
+//
+// english comes right before dictionary, and leaves the words in the guild's dictionary alone,
+// so that they are read as registered there and the registered readings are not read as English.
+//
+// `12:30:45` is read by number after uri has dropped numbers within URIs, and before emoji takes `:30:` for an emoji.
+pub static STAGES: &[&dyn FilterStage] = &[
+    &stages::LegacyCommand,
+    &stages::LegacyPing,
+    &stages::IgnorePrefix,
+    &timestamp::Timestamp,
+    &stages::ExternalEmoji,
+    &stages::BotCommandPrefix,
+    &stages::Uri,
+    &number::Number,
+    &stages::Emoji,
+    &stages::UnicodeEmoji,
+    &attachment::Attachment,
+    &stages::Codeblock,
+    &markdown::Markdown,
+    &stages::Whitespace,
+    &repetition::Repetition,
+    &english::English,
+    &stages::Dictionary,
+];
+
+pub fn find_stage(name: &str) -> Option<&'static dyn FilterStage> {
+    STAGES.iter().find(|s| s.name() == name).copied()
+}
+
+/// What is read for a message, and in which voice unless in the author's.
+pub struct Speech {
+    pub text: String,
+    pub voice: Option<Voice>,
+}
+
+impl From<String> for Speech {
+    fn from(text: String) -> Self {
+        Self { text, voice: None }
+    }
+}
+
+pub async fn filter<T>(ctx: T, mes: &'_ Message) -> Option<Speech>
+where
+    T: CacheHttp + AsRef<Cache> + Copy,
+{
+    let guild_id = mes.guild_id?;
+    let linked = INMEMORY_DB.get_instance(guild_id)?;
+    let settings = PERSISTENT_DB.get_guild_settings(guild_id);
+    let dictionary = PERSISTENT_DB.dictionary_replacer();
+
+    if !linked.contains(&mes.channel_id) {
+        if !settings.read_threads {
+            return None;
+        }
+
+        let parent = ctx
+            .as_ref()
+            .guild(guild_id)?
+            .threads
+            .iter()
+            .find(|t| t.id == mes.channel_id)?
+            .parent_id?;
+
+        if !linked.contains(&parent) {
+            return None;
+        }
+    }
+
+    if mes.author.bot {
+        return None;
+    }
+
+    if is_forward(mes) {
+        return Some(read_forward(ctx, mes, &settings, &dictionary).await.into());
+    }
+
+    let language = match settings.foreign_language.mode {
+        ForeignLanguageMode::Read => None,
+        _ => language::detect(&mes.content),
+    };
+
+    let (text, voice) = if let Some(language) = language {
+        read_foreign(ctx, mes, &settings, &dictionary, language).await?
+    } else {
+        let text = run_stages(
+            mention::resolve_mentions(ctx, mes).await,
+            &StageContext {
+                message: mes,
+                settings: &settings,
+                dictionary: &dictionary,
+            },
+        )?;
+
+        (text, None)
+    };
+
+    match mes.referenced_message.as_deref() {
+        Some(replied) if settings.reply_context.enabled && mes.kind == MessageType::InlineReply => {
+            let reading = PERSISTENT_DB.get_name_reading(guild_id, replied.author.id);
+            let name = reading.unwrap_or_else(|| {
+                ctx.as_ref().guild(guild_id).map_or_else(
+                    || {
+                        replied
+                            .author
+                            .global_name
+                            .as_ref()
+                            .unwrap_or(&replied.author.name)
+                            .clone()
+                    },
+                    |g| member_name(&g, &replied.author),
+                )
+            });
+
+            Some(Speech {
+                text: settings.reply_context.template.replace("{name}", &name) + &text,
+                voice,
+            })
+        }
+        _ => Some(Speech { text, voice }),
+    }
+}
+
+/// Reads a message which is not in Japanese the way the guild likes, in the voice for the language if routed.
+async fn read_foreign<T>(
+    ctx: T,
+    mes: &Message,
+    settings: &GuildSettings,
+    dictionary: &Replacer,
+    language: &str,
+) -> Option<(String, Option<Voice>)>
+where
+    T: CacheHttp + AsRef<Cache> + Copy,
+{
+    let foreign = &settings.foreign_language;
+    let voice = foreign
+        .voices
+        .get(language)
+        .and_then(|voice| Voice::parse(voice));
+
+    match (foreign.mode, voice) {
+        (ForeignLanguageMode::Skip, _) => None,
+        (ForeignLanguageMode::Route, Some(voice)) => {
+            let settings = GuildSettings {
+                disabled_stages: settings
+                    .disabled_stages
+                    .iter()
+                    .map(String::as_str)
+                    .chain(language::JAPANESE_STAGES.iter().copied())
+                    .map(ToString::to_string)
+                    .collect(),
+                ..settings.clone()
+            };
+
+            let text = run_stages(
+                mention::resolve_mentions(ctx, mes).await,
+                &StageContext {
+                    message: mes,
+                    settings: &settings,
+                    dictionary,
+                },
+            )?;
+
+            Some((text, Some(voice)))
+        }
+        _ => replace_foreign(&StageContext {
+            message: mes,
+            settings,
+            dictionary,
+        })
+        .map(|text| (text, None)),
+    }
+}
+
+/// Stages which only decide whether a message is read at all, such as commands for other bots.
+const SUPPRESSING_STAGES: &[&str] = &[
+    "legacy_command",
+    "legacy_ping",
+    "ignore_prefix",
+    "bot_command_prefix",
+];
+
+/// The replacement for a message in another language, unless the message would not be read anyway.
+/// Attachments are still told.
+fn replace_foreign(ctx: &StageContext) -> Option<String> {
+    run_some_stages(ctx.message.content.clone(), ctx, SUPPRESSING_STAGES)?;
+
+    run_some_stages(
+        ctx.settings.foreign_language.replacement.clone(),
+        ctx,
+        &["attachment"],
+    )
+}
+
+/// A forwarded message is a regular message referring to another one, without content of its own.
+fn is_forward(mes: &Message) -> bool {
+    mes.kind == MessageType::Regular && mes.message_reference.is_some() && mes.content.is_empty()
+}
+
+/// Serenity does not deserialize message snapshots, so the forwarded message is fetched instead.
+/// It cannot be read if the bot has no access to its channel.
+async fn read_forward<T>(
+    ctx: T,
+    mes: &Message,
+    settings: &GuildSettings,
+    dictionary: &Replacer,
+) -> String
+where
+    T: CacheHttp,
+{
+    let reference = mes.message_reference.as_ref().unwrap();
+
+    let original = match reference.message_id {
+        Some(id) => reference.channel_id.message(ctx, id).await.ok(),
+        None => None,
+    };
+
+    let body = original.and_then(|original| {
+        run_stages(
+            original.content.clone(),
+            &StageContext {
+                message: &original,
+                settings,
+                dictionary,
+            },
+        )
+    });
+
+    match body {
+        Some(body) => settings.forward.template.replace("{body}", &body),
+        None => settings.forward.unavailable.clone(),
+    }
+}
+
+/// The nickname in the guild, or else the display name of the user.
+fn member_name(guild: &Guild, user: &User) -> String {
+    guild
+        .members
+        .get(&user.id)
+        .and_then(|m| m.nick.clone())
+        .unwrap_or_else(|| user.global_name.as_ref().unwrap_or(&user.name).clone())
+}
+
+/// Runs the stages which are not disabled by the guild, in order.
+pub fn run_stages(text: String, ctx: &StageContext) -> Option<String> {
+    STAGES
+        .iter()
+        .filter(|s| !ctx.settings.disabled_stages.iter().any(|d| d == s.name()))
+        .try_fold(text, |text, stage| stage.apply(text, ctx))
+}
+
+/// Runs the stages among `names` which are not disabled by the guild, in order.
+fn run_some_stages(text: String, ctx: &StageContext, names: &[&str]) -> Option<String> {
+    STAGES
+        .iter()
+        .filter(|s| names.contains(&s.name()))
+        .filter(|s| !ctx.settings.disabled_stages.iter().any(|d| d == s.name()))
+        .try_fold(text, |text, stage| stage.apply(text, ctx))
+}
+
+/// Builds the text spoken for an edit of a message whose original text has already been spoken.
+/// `before` and `after` are both expected to be outputs of [`filter`].
+pub fn correction(before: &str, after: &str, mode: EditReadMode) -> Option<String> {
+    if before == after {
+        return None;
+    }
+
+    let body = match mode {
+        EditReadMode::Off => return None,
+        EditReadMode::Full => after,
+        EditReadMode::Diff => edited_part(before, after),
+    };
+
+    Some(format!("訂正、{body}"))
+}
+
+/// Returns the phrase(s) of `after` which differ from `before`.
+/// The changed range is widened to whitespace or punctuation, since reading a fragment of a word is meaningless.
+fn edited_part<'a>(before: &str, after: &'a str) -> &'a str {
+    fn is_phrase_boundary(c: char) -> bool {
+        c.is_whitespace() || "、。，．,.!?！？".contains(c)
+    }
+
+    let prefix: usize = before
+        .chars()
+        .zip(after.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(_, b)| b.len_utf8())
+        .sum();
+
+    let suffix: usize = before[prefix..]
+        .chars()
+        .rev()
+        .zip(after[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(_, b)| b.len_utf8())
+        .sum();
+
+    // Nothing has been added, so there is no phrase to point at.
+    if prefix + suffix == after.len() {
+        return after;
+    }
+
+    let start = after[..prefix]
+        .rfind(is_phrase_boundary)
+        .map_or(0, |i| i + after[i..].chars().next().unwrap().len_utf8());
+
+    let end = after[after.len() - suffix..]
+        .find(is_phrase_boundary)
+        .map_or(after.len(), |i| after.len() - suffix + i);
+
+    let part = after[start..end].trim();
+
+    if part.is_empty() {
+        after
+    } else {
+        part
+    }
+}
+
+#[test]
+fn stages_unit_test() {
+    let message = Message::default();
+    let mut settings = GuildSettings::default();
+    let dictionary = Replacer::new(&[("bot".to_string(), "ボット君".to_string())].into());
+
+    let run = |text: &str, settings: &GuildSettings| {
+        run_stages(
+            text.to_string(),
+            &StageContext {
+                message: &message,
+                settings,
+                dictionary: &dictionary,
+            },
+        )
+    };
+
+    assert_eq!(run("hello", &settings), Some("hello".to_string()));
+    assert_eq!(run(";hello", &settings), None);
+    assert_eq!(run("   ", &settings), None);
+    assert_eq!(
+        run("see http://metaba.su", &settings),
+        Some("see 。URI省略。".to_string())
+    );
+
+    settings.disabled_stages.push("uri".to_string());
+    settings.disabled_stages.push("ignore_prefix".to_string());
+    assert_eq!(
+        run("see http://metaba.su", &settings),
+        Some("see http://metaba.su".to_string())
+    );
+    assert_eq!(run(";hello", &settings), Some(";hello".to_string()));
+
+    settings.disabled_stages.clear();
+    assert_eq!(
+        run("20:40に https://example.com/3/4 で", &settings),
+        Some("20時40分に 。URI省略。 で".to_string())
+    );
+    assert_eq!(run("12:30:45", &settings), Some("12時30分45秒".to_string()));
+    assert_eq!(run("bot!", &settings), Some("ボット君!".to_string()));
+
+    // Words in the dictionary are left for it by english.
+    settings.read_english = true;
+    assert_eq!(run("bot!", &settings), Some("ボット君!".to_string()));
+    settings.read_english = false;
+    assert_eq!(
+        run("答えは||https://example.com||", &settings),
+        Some("答えは伏せ字".to_string())
+    );
+    assert_eq!(
+        run("`git clone https://example.com`", &settings),
+        Some("。コード省略。".to_string())
+    );
+
+    let replace = |content: &str| {
+        let mut message = Message::default();
+        message.content = content.to_string();
+
+        replace_foreign(&StageContext {
+            message: &message,
+            settings: &GuildSettings::default(),
+            dictionary: &dictionary,
+        })
+    };
+    assert_eq!(
+        replace("I made a bot for Discord"),
+        Some("外国語のメッセージ".to_string())
+    );
+    assert_eq!(replace("!play https://example.com/song"), None);
+    assert_eq!(replace(";note to myself"), None);
+
+    let mut names: Vec<_> = STAGES.iter().map(|s| s.name()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), STAGES.len());
+}
+
+#[test]
+fn correction_unit_test() {
+    assert_eq!(correction("hello", "hello", EditReadMode::Diff), None);
+    assert_eq!(correction("hello", "world", EditReadMode::Off), None);
+    assert_eq!(
+        correction("明日いく", "明後日いく", EditReadMode::Full),
+        Some("訂正、明後日いく".to_string())
+    );
+    assert_eq!(
+        correction("明日いく", "明後日いく", EditReadMode::Diff),
+        Some("訂正、明後日いく".to_string())
+    );
+    assert_eq!(
+        correction(
+            "今日は晴れ。明日は雨",
+            "今日は晴れ。明後日は雨",
+            EditReadMode::Diff
+        ),
+        Some("訂正、明後日は雨".to_string())
+    );
+    assert_eq!(
+        correction("see you at 7 pm", "see you at 8 pm", EditReadMode::Diff),
+        Some("訂正、8".to_string())
+    );
+    assert_eq!(
+        correction(
+            "see you at 7 pm today",
+            "see you at 7 pm",
+            EditReadMode::Diff
+        ),
+        Some("訂正、see you at 7 pm".to_string())
+    );
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::{FilterStage, StageContext};
+use crate::replacer::Replacer;
+
+// regex crate's named capture
+#[allow(clippy::invalid_regex)]
+static DATE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?<year>\d{4})(?<sep1>[-/.])(?<month>\d{1,2})(?<sep2>[-/.])(?<day>\d{1,2})")
+        .unwrap()
+});
+#[allow(clippy::invalid_regex)]
+static TIME_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?<hour>\d{1,2}):(?<minute>\d{2})(?::(?<second>\d{2}))?").unwrap());
+#[allow(clippy::invalid_regex)]
+static RANGE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?<from>\d)[~〜～](?<to>\d)").unwrap());
+// A hyphen only makes a range between times, since dates and phone numbers have them too.
+#[allow(clippy::invalid_regex)]
+static TIME_RANGE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?<from>\d{1,2}:\d{2})-(?<to>\d{1,2}:\d{2})").unwrap());
+static SEPARATED_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d{1,3}(?:,\d{3})+").unwrap());
+#[allow(clippy::invalid_regex)]
+static SLASH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?<left>\d+)/(?<right>\d+)(?<weekday>\s*[(（][月火水木金土日][)）]|[月火水木金土日]曜)?",
+    )
+    .unwrap()
+});
+#[allow(clippy::invalid_regex)]
+static UNIT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    let mut units: Vec<_> = UNITS.iter().map(|(unit, _)| *unit).collect();
+    // Alternatives are tried in order, so `mg` must come before `m`.
+    units.sort_unstable_by_key(|unit| std::cmp::Reverse(unit.len()));
+
+    let units: Vec<_> = units.into_iter().map(regex::escape).collect();
+
+    Regex::new(&format!(
+        r"(?<number>\d+(?:\.\d+)?)(?<unit>{})(?<letter>[A-Za-z]?)",
+        units.join("|")
+    ))
+    .unwrap()
+});
+static SYMBOLS: Lazy<Replacer> = Lazy::new(|| {
+    Replacer::new(
+        &SYMBOL_READINGS
+            .iter()
+            .map(|(symbol, reading)| (symbol.to_string(), reading.to_string()))
+            .collect::<HashMap<_, _>>(),
+    )
+});
+
+const UNITS: &[(&str, &str)] = &[
+    ("%", "パーセント"),
+    ("％", "パーセント"),
+    ("℃", "度"),
+    ("°C", "度"),
+    ("mm", "ミリメートル"),
+    ("cm", "センチメートル"),
+    ("m", "メートル"),
+    ("km", "キロメートル"),
+    ("km/h", "キロメートル毎時"),
+    ("mg", "ミリグラム"),
+    ("g", "グラム"),
+    ("kg", "キログラム"),
+    ("mL", "ミリリットル"),
+    ("L", "リットル"),
+    ("kcal", "キロカロリー"),
+    ("KB", "キロバイト"),
+    ("kB", "キロバイト"),
+    ("MB", "メガバイト"),
+    ("GB", "ギガバイト"),
+    ("TB", "テラバイト"),
+    ("Mbps", "メガビーピーエス"),
+    ("Hz", "ヘルツ"),
+    ("kHz", "キロヘルツ"),
+    ("MHz", "メガヘルツ"),
+    ("GHz", "ギガヘルツ"),
+    ("W", "ワット"),
+    ("kW", "キロワット"),
+    ("mAh", "ミリアンペアアワー"),
+    ("ms", "ミリ秒"),
+    ("fps", "エフピーエス"),
+    ("px", "ピクセル"),
+];
+
+const SYMBOL_READINGS: &[(&str, &str)] = &[
+    ("C++", "シープラスプラス"),
+    ("C#", "シーシャープ"),
+    ("F#", "エフシャープ"),
+    ("&", "アンド"),
+    ("＆", "アンド"),
+    ("±", "プラスマイナス"),
+    ("≒", "ニアリーイコール"),
+];
+
+/// Reads dates, times, fractions, separated numbers, units and symbols the way they are spoken.
+/// This must run after uri, so that numbers in an URI are left for it to drop.
+pub struct Number;
+
+impl FilterStage for Number {
+    fn name(&self) -> &'static str {
+        "number"
+    }
+
+    fn apply(&self, text: String, _: &StageContext) -> Option<String> {
+        Some(normalize_numbers(&text))
+    }
+}
+
+fn normalize_numbers(mes: &str) -> String {
+    // Ranges go first, because `10:00~12:00` no longer has digits around `~` once the times are read.
+    let mes = RANGE_REGEX.replace_all(mes, "${from}から${to}");
+    let mes = TIME_RANGE_REGEX.replace_all(&mes, "${from}から${to}");
+    let mes = replace_numbers(&mes, &DATE_REGEX, read_date);
+    let mes = replace_numbers(&mes, &TIME_REGEX, read_time);
+    let mes = replace_numbers(&mes, &SEPARATED_REGEX, |caps| {
+        Some(caps[0].replace(',', ""))
+    });
+    let mes = replace_numbers(&mes, &SLASH_REGEX, read_slash);
+    let mes = replace_numbers(&mes, &UNIT_REGEX, read_unit);
+
+    SYMBOLS.replace_all(&mes)
+}
+
+/// Replaces matches of `regex` which are not a part of a longer number like `1.2.3` or `1:2:3:4`.
+/// Those for which `read` returns `None` are left as they are.
+fn replace_numbers(mes: &str, regex: &Regex, read: impl Fn(&Captures) -> Option<String>) -> String {
+    regex
+        .replace_all(mes, |caps: &Captures| {
+            let whole = caps.get(0).unwrap();
+            let isolated = !continues_number(mes[..whole.start()].chars().rev())
+                && !continues_number(mes[whole.end()..].chars());
+
+            isolated
+                .then(|| read(caps))
+                .flatten()
+                .unwrap_or_else(|| whole.as_str().to_string())
+        })
+        .into_owned()
+}
+
+/// Whether `chars`, read away from a match, go on with the number.
+/// A separator only does so if a digit follows it, so that `10%.` ends a sentence.
+fn continues_number(mut chars: impl Iterator<Item = char>) -> bool {
+    match chars.next() {
+        Some(c) if c.is_ascii_digit() => true,
+        Some('.' | ',' | '/' | ':' | '-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+fn read_date(caps: &Captures) -> Option<String> {
+    let month: u32 = caps["month"].parse().ok()?;
+    let day: u32 = caps["day"].parse().ok()?;
+
+    (caps["sep1"] == caps["sep2"] && (1..=12).contains(&month) && (1..=31).contains(&day))
+        .then(|| format!("{}年{month}月{day}日", &caps["year"]))
+}
+
+fn read_time(caps: &Captures) -> Option<String> {
+    let hour: u32 = caps["hour"].parse().ok()?;
+    let minute: u32 = caps["minute"].parse().ok()?;
+    let second: Option<u32> = caps.name("second").and_then(|s| s.as_str().parse().ok());
+
+    // Japanese timetables count past midnight up to 30時.
+    if hour > 30 || minute >= 60 || second.is_some_and(|s| s >= 60) {
+        return None;
+    }
+
+    Some(match second.filter(|s| *s > 0) {
+        Some(second) => format!("{hour}時{minute}分{second}秒"),
+        None if minute > 0 => format!("{hour}時{minute}分"),
+        None => format!("{hour}時"),
+    })
+}
+
+/// Read as a fraction, e.g. `3/4` is 4分の3, unless a weekday follows to tell it is a date,
+/// e.g. `3/4(火)` is 3月4日(火). Dates with a year are left to [`read_date`].
+fn read_slash(caps: &Captures) -> Option<String> {
+    let left = &caps["left"];
+    let right = &caps["right"];
+    let weekday = caps.name("weekday").map_or("", |w| w.as_str());
+
+    if let (Ok(month), Ok(day)) = (left.parse::<u32>(), right.parse::<u32>()) {
+        if !weekday.is_empty() && (1..=12).contains(&month) && (1..=31).contains(&day) {
+            return Some(format!("{month}月{day}日{weekday}"));
+        }
+    }
+
+    // Nothing is divided by zero.
+    if right.trim_start_matches('0').is_empty() {
+        return None;
+    }
+
+    Some(format!("{right}分の{left}{weekday}"))
+}
+
+fn read_unit(caps: &Captures) -> Option<String> {
+    // `5min` is not `5m` followed by `in`.
+    if !caps["letter"].is_empty() {
+        return None;
+    }
+
+    let (_, reading) = UNITS.iter().find(|(unit, _)| *unit == &caps["unit"])?;
+
+    Some(format!("{}{reading}", &caps["number"]))
+}
+
+#[test]
+fn number_unit_test() {
+    let cases = [
+        ("hello", "hello"),
+        ("2026-10-18", "2026年10月18日"),
+        ("2026/1/5に", "2026年1月5日に"),
+        ("2026.10.18", "2026年10月18日"),
+        ("2026-10/18", "2026-10/18"),
+        ("2026-13-01", "2026-13-01"),
+        ("12:30", "12時30分"),
+        ("20:40に秋葉原にて待つ", "20時40分に秋葉原にて待つ"),
+        ("9:00", "9時"),
+        ("0:00:05", "0時0分5秒"),
+        ("25:30", "25時30分"),
+        ("99:30", "99:30"),
+        ("1:2:3:4", "1:2:3:4"),
+        ("3/4", "4分の3"),
+        ("1/2", "2分の1"),
+        ("1/3", "3分の1"),
+        ("3/4(火)", "3月4日(火)"),
+        ("10/18（土）に", "10月18日（土）に"),
+        ("12/31 (水)", "12月31日 (水)"),
+        ("12/31水曜", "12月31日水曜"),
+        ("13/2", "2分の13"),
+        ("13/2(月)", "2分の13(月)"),
+        ("1/32", "32分の1"),
+        ("0/5", "5分の0"),
+        ("1/0", "1/0"),
+        ("1/2/3", "1/2/3"),
+        ("10%", "10パーセント"),
+        ("10%.", "10パーセント."),
+        ("1.5％", "1.5パーセント"),
+        ("1,000円", "1000円"),
+        ("1,234,567", "1234567"),
+        ("1,2,3", "1,2,3"),
+        ("1,0000", "1,0000"),
+        ("1,000kg", "1000キログラム"),
+        ("5kg", "5キログラム"),
+        ("500mg", "500ミリグラム"),
+        ("100m走", "100メートル走"),
+        ("60km/h", "60キロメートル毎時"),
+        ("5min", "5min"),
+        ("16GB", "16ギガバイト"),
+        ("36℃", "36度"),
+        ("3~5個", "3から5個"),
+        ("10:00〜12:00", "10時から12時"),
+        ("10:00-12:00", "10時から12時"),
+        ("9:30-17:00に", "9時30分から17時に"),
+        ("3-5", "3-5"),
+        ("すごい~", "すごい~"),
+        ("C++とC#", "シープラスプラスとシーシャープ"),
+        ("R&D", "RアンドD"),
+    ];
+
+    for (mes, expected) in cases {
+        assert_eq!(normalize_numbers(mes), expected, "{mes}");
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use super::{FilterStage, StageContext};
+
+/// Tokens longer than this are not looked for.
+const MAX_TOKEN_CHARS: usize = 4;
+
+/// Reads runs of net slang and shortens other runs of a repeated character or token.
+pub struct Repetition;
+
+impl FilterStage for Repetition {
+    fn name(&self) -> &'static str {
+        "repetition"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        let settings = &ctx.settings.repetition;
+        // A limit of zero would delete every character but digits.
+        let limit = settings
+            .limit
+            .and_then(|l| usize::try_from(l).ok())
+            .filter(|l| *l > 0);
+
+        Some(compress_repetition(&text, &settings.slang, limit))
+    }
+}
+
+fn compress_repetition(
+    mes: &str,
+    slang: &BTreeMap<String, String>,
+    limit: Option<usize>,
+) -> String {
+    let chars: Vec<char> = mes.chars().collect();
+    let mut ret = String::with_capacity(mes.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if let Some((len, reading)) = slang_at(&chars, i, slang) {
+            ret.push_str(reading);
+            i += len;
+            continue;
+        }
+
+        if let Some((kept, len)) = limit.and_then(|l| repetition_at(&chars, i, l)) {
+            ret.extend(&chars[i..i + kept]);
+            i += len;
+            continue;
+        }
+
+        ret.push(chars[i]);
+        i += 1;
+    }
+
+    ret
+}
+
+/// Slang only matches on its own, so that `w` in `wow` or `888` in `18888` stay as they are.
+fn slang_at<'a>(
+    chars: &[char],
+    i: usize,
+    slang: &'a BTreeMap<String, String>,
+) -> Option<(usize, &'a str)> {
+    let is_word = |c: &char| c.is_ascii_alphanumeric();
+
+    if i > 0 && is_word(&chars[i - 1]) {
+        return None;
+    }
+
+    slang.iter().find_map(|(key, reading)| {
+        let key: Vec<char> = key.chars().collect();
+
+        if key.is_empty() {
+            return None;
+        }
+
+        // `ww` matches a run of at least two `w`s, or a single one right after Japanese as in `面白いw`,
+        // while `lol` matches `lol`, `lollol` and so on.
+        let (token, min) = if key.iter().all(|c| *c == key[0]) {
+            let after_japanese = i > 0 && is_japanese(chars[i - 1]);
+            (&key[..1], if after_japanese { 1 } else { key.len() })
+        } else {
+            (&key[..], 1)
+        };
+
+        let count = repeats(chars, i, token);
+        let end = i + token.len() * count;
+
+        // `www.example.com` and `w/o` go on as a word.
+        let continues = chars.get(end).is_some_and(is_word)
+            || (chars.get(end).is_some_and(|c| matches!(c, '.' | '/'))
+                && chars.get(end + 1).is_some_and(is_word));
+
+        (count >= min && !continues).then_some((end - i, reading.as_str()))
+    })
+}
+
+fn is_japanese(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}')
+}
+
+/// Finds a token repeated more than `limit` times at `i`.
+/// Returns the number of characters to keep, and the length of the whole run.
+/// Digits are never shortened, since `1000000` is a number and not a repetition.
+fn repetition_at(chars: &[char], i: usize, limit: usize) -> Option<(usize, usize)> {
+    (1..=MAX_TOKEN_CHARS)
+        .take_while(|token| i + token <= chars.len())
+        .map(|token| (token, repeats(chars, i, &chars[i..i + token])))
+        .find(|(token, count)| {
+            *count > limit && !chars[i..i + token].iter().any(char::is_ascii_digit)
+        })
+        .map(|(token, count)| (token * limit, token * count))
+}
+
+fn repeats(chars: &[char], i: usize, token: &[char]) -> usize {
+    if token.is_empty() {
+        return 0;
+    }
+
+    chars[i..]
+        .chunks_exact(token.len())
+        .take_while(|c| *c == token)
+        .count()
+}
+
+#[test]
+fn repetition_unit_test() {
+    let slang = crate::settings::Repetition::default().slang;
+    let compress = |mes| compress_repetition(mes, &slang, Some(3));
+
+    assert_eq!(compress("hello"), "hello");
+    assert_eq!(compress("wwwwwwww"), "わら");
+    assert_eq!(compress("面白いw"), "面白いわら");
+    assert_eq!(compress("それなｗｗｗ"), "それなわら");
+    assert_eq!(compress("wow"), "wow");
+    assert_eq!(compress("888888"), "パチパチ");
+    assert_eq!(compress("88"), "88");
+    assert_eq!(compress("18888"), "18888");
+    assert_eq!(compress("1000000円"), "1000000円");
+    assert_eq!(compress("草草草草草草"), "草草草");
+    assert_eq!(compress("すごーーーーーい"), "すごーーーい");
+    assert_eq!(compress("あはあはあはあはあは"), "あはあはあは");
+    assert_eq!(compress("!!!!!!"), "!!!");
+    assert_eq!(compress("草草草"), "草草草");
+    assert_eq!(compress("草w"), "草わら");
+    assert_eq!(compress("w"), "w");
+    assert_eq!(compress("ww"), "わら");
+    assert_eq!(compress("www.example.com"), "www.example.com");
+    assert_eq!(compress("w/o"), "w/o");
+    assert_eq!(compress("ww/"), "わら/");
+
+    let compress = |mes| compress_repetition(mes, &BTreeMap::new(), None);
+
+    assert_eq!(compress("wwwwwwww"), "wwwwwwww");
+
+    let slang = BTreeMap::from([("lol".to_string(), "ろる".to_string())]);
+    let compress = |mes| compress_repetition(mes, &slang, Some(2));
+
+    assert_eq!(compress("lollollol"), "ろる");
+    assert_eq!(compress("lolly"), "lolly");
+    assert_eq!(compress("wwwww"), "ww");
+}
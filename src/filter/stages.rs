@@ -0,0 +1,561 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::{NoExpand, Regex};
+
+use super::{FilterStage, StageContext};
+use crate::db::{EMOJI_DB, PERSISTENT_DB};
+
+static CODEBLOCK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?sm)```.+```").unwrap());
+// regex crate's named capture
+#[allow(clippy::invalid_regex)]
+static EXTERNAL_EMOJI_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<a?:(?<name>\w+):\d+>").unwrap());
+#[allow(clippy::invalid_regex)]
+static EMOJI_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r":(?<name>\w+):").unwrap());
+// Backquotes and `||` end a URI, so that code and spoilers around it are left to [`Markdown`](super::markdown::Markdown).
+static URI_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[A-Za-z][A-Za-z0-9+\-.]*:(?:[^\s|`]|\|[^\s|`])+").unwrap());
+
+pub struct LegacyCommand;
+
+impl FilterStage for LegacyCommand {
+    fn name(&self) -> &'static str {
+        "legacy_command"
+    }
+
+    fn apply(&self, text: String, _: &StageContext) -> Option<String> {
+        legacy_command_compatibility(&text)?;
+        Some(text)
+    }
+}
+
+pub struct LegacyPing;
+
+impl FilterStage for LegacyPing {
+    fn name(&self) -> &'static str {
+        "legacy_ping"
+    }
+
+    fn apply(&self, text: String, _: &StageContext) -> Option<String> {
+        legacy_ping_command_compatibility(&text)?;
+        Some(text)
+    }
+}
+
+pub struct IgnorePrefix;
+
+impl FilterStage for IgnorePrefix {
+    fn name(&self) -> &'static str {
+        "ignore_prefix"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        suppress_by_ignore_prefix(&text, &ctx.settings.ignore_prefix)?;
+        Some(text)
+    }
+}
+
+pub struct ExternalEmoji;
+
+impl FilterStage for ExternalEmoji {
+    fn name(&self) -> &'static str {
+        "external_emoji"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        Some(
+            replace_external_emoji(&text, &emoji_readings(ctx), ctx.settings.read_emoji_names)
+                .into_owned(),
+        )
+    }
+}
+
+pub struct BotCommandPrefix;
+
+impl FilterStage for BotCommandPrefix {
+    fn name(&self) -> &'static str {
+        "bot_command_prefix"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        suppress_by_bot_command_prefix(&text, &ctx.settings.bot_command_prefix)?;
+        Some(text)
+    }
+}
+
+pub struct Uri;
+
+impl FilterStage for Uri {
+    fn name(&self) -> &'static str {
+        "uri"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        let settings = ctx.settings;
+        let domain_replacement = settings
+            .read_domains
+            .then_some(settings.domain_replacement.as_str());
+
+        Some(replace_uri(&text, &settings.uri_replacement, domain_replacement).into_owned())
+    }
+}
+
+pub struct Emoji;
+
+impl FilterStage for Emoji {
+    fn name(&self) -> &'static str {
+        "emoji"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        Some(replace_emoji(&text, &emoji_readings(ctx), ctx.settings.read_emoji_names).into_owned())
+    }
+}
+
+fn emoji_readings(ctx: &StageContext) -> HashMap<String, String> {
+    ctx.message
+        .guild_id
+        .map(|g| PERSISTENT_DB.get_emoji_dictionary(g))
+        .unwrap_or_default()
+}
+
+pub struct UnicodeEmoji;
+
+impl FilterStage for UnicodeEmoji {
+    fn name(&self) -> &'static str {
+        "unicode_emoji"
+    }
+
+    fn apply(&self, text: String, _: &StageContext) -> Option<String> {
+        Some(replace_unicode_emoji(&text))
+    }
+}
+
+pub struct Codeblock;
+
+impl FilterStage for Codeblock {
+    fn name(&self) -> &'static str {
+        "codeblock"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        Some(replace_codeblock(&text, &ctx.settings.code_replacement).into_owned())
+    }
+}
+
+pub struct Whitespace;
+
+impl FilterStage for Whitespace {
+    fn name(&self) -> &'static str {
+        "whitespace"
+    }
+
+    fn apply(&self, text: String, _: &StageContext) -> Option<String> {
+        suppress_whitespaces(&text)?;
+        Some(text)
+    }
+}
+
+pub struct Dictionary;
+
+impl FilterStage for Dictionary {
+    fn name(&self) -> &'static str {
+        "dictionary"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        Some(ctx.dictionary.replace_all(&text))
+    }
+}
+
+#[inline]
+fn legacy_command_compatibility(mes: &str) -> Option<&str> {
+    (!mes.starts_with('~')).then_some(mes)
+}
+
+#[inline]
+fn legacy_ping_command_compatibility(mes: &str) -> Option<&str> {
+    (mes != "ping").then_some(mes)
+}
+
+#[inline]
+fn suppress_by_ignore_prefix<'a>(mes: &'a str, prefix: &str) -> Option<&'a str> {
+    (prefix.is_empty() || !mes.starts_with(prefix) || mes.starts_with(&prefix.repeat(2)))
+        .then_some(mes)
+}
+
+#[inline]
+fn suppress_by_bot_command_prefix<'a>(mes: &'a str, prefix: &str) -> Option<&'a str> {
+    (prefix.is_empty() || !mes.starts_with(prefix)).then_some(mes)
+}
+
+#[inline]
+fn suppress_whitespaces(mes: &str) -> Option<&str> {
+    (!mes.trim().is_empty()).then_some(mes)
+}
+
+/// Readings of well-known domains. Subdomains are read as their parent.
+const KNOWN_DOMAINS: &[(&str, &str)] = &[
+    ("youtube.com", "ユーチューブ"),
+    ("youtu.be", "ユーチューブ"),
+    ("twitter.com", "ツイッター"),
+    ("x.com", "エックス"),
+    ("discord.com", "ディスコード"),
+    ("discord.gg", "ディスコード"),
+    ("discordapp.com", "ディスコード"),
+    ("nicovideo.jp", "ニコニコ動画"),
+    ("nico.ms", "ニコニコ動画"),
+    ("google.com", "グーグル"),
+    ("amazon.co.jp", "アマゾン"),
+    ("amzn.to", "アマゾン"),
+    ("wikipedia.org", "ウィキペディア"),
+    ("pixiv.net", "ピクシブ"),
+    ("instagram.com", "インスタグラム"),
+    ("tiktok.com", "ティックトック"),
+    ("twitch.tv", "ツイッチ"),
+    ("reddit.com", "レディット"),
+    ("spotify.com", "スポティファイ"),
+];
+
+/// `domain_replacement` is used for HTTP(S) URLs if given, and `replacement` for every other URI.
+fn replace_uri<'a>(
+    mes: &'a str,
+    replacement: &str,
+    domain_replacement: Option<&str>,
+) -> Cow<'a, str> {
+    let Some(domain_replacement) = domain_replacement else {
+        return URI_REGEX.replace_all(mes, NoExpand(replacement));
+    };
+
+    URI_REGEX.replace_all(mes, |caps: &regex::Captures| {
+        read_domain(&caps[0]).map_or_else(
+            || replacement.to_string(),
+            |domain| domain_replacement.replace("{domain}", &domain),
+        )
+    })
+}
+
+fn read_domain(uri: &str) -> Option<String> {
+    let url = reqwest::Url::parse(uri).ok()?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    let known = KNOWN_DOMAINS.iter().find(|(domain, _)| {
+        host == *domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.'))
+    });
+
+    Some(known.map_or_else(|| host.to_string(), |(_, reading)| (*reading).to_string()))
+}
+
+#[inline]
+fn replace_external_emoji<'a>(
+    mes: &'a str,
+    readings: &HashMap<String, String>,
+    read_names: bool,
+) -> Cow<'a, str> {
+    EXTERNAL_EMOJI_REGEX.replace_all(mes, |caps: &regex::Captures| {
+        read_emoji(&caps["name"], readings, read_names)
+    })
+}
+
+#[inline]
+fn replace_emoji<'a>(
+    mes: &'a str,
+    readings: &HashMap<String, String>,
+    read_names: bool,
+) -> Cow<'a, str> {
+    EMOJI_REGEX.replace_all(mes, |caps: &regex::Captures| {
+        read_emoji(&caps["name"], readings, read_names)
+    })
+}
+
+/// The guild's reading of the emoji takes priority over its name.
+fn read_emoji(name: &str, readings: &HashMap<String, String>, read_names: bool) -> String {
+    match readings.get(name) {
+        Some(reading) => reading.clone(),
+        None if read_names => name.replace('_', " "),
+        None => String::new(),
+    }
+}
+
+#[inline]
+fn replace_codeblock<'a>(mes: &'a str, replacement: &str) -> Cow<'a, str> {
+    CODEBLOCK_REGEX.replace_all(mes, NoExpand(replacement))
+}
+
+#[inline]
+fn replace_unicode_emoji(mes: &str) -> String {
+    EMOJI_DB.replace_all(mes)
+}
+
+#[test]
+fn legacy_command_unit_test() {
+    assert_eq!(legacy_command_compatibility("~join"), None);
+    assert_eq!(legacy_command_compatibility("hello"), Some("hello"));
+}
+
+#[test]
+fn legacy_ping_unit_test() {
+    assert_eq!(legacy_ping_command_compatibility("ping"), None);
+    assert_eq!(legacy_ping_command_compatibility("hello"), Some("hello"));
+}
+
+#[test]
+fn ignore_prefix_unit_test() {
+    let settings = crate::settings::GuildSettings::default();
+
+    assert_eq!(
+        suppress_by_ignore_prefix("hello", &settings.ignore_prefix),
+        Some("hello")
+    );
+    assert_eq!(
+        suppress_by_ignore_prefix(";hello", &settings.ignore_prefix),
+        None
+    );
+    assert_eq!(
+        suppress_by_ignore_prefix(";;hello", &settings.ignore_prefix),
+        Some(";;hello")
+    );
+    assert_eq!(suppress_by_ignore_prefix(";hello", ""), Some(";hello"));
+    assert_eq!(suppress_by_ignore_prefix("//hello", "//"), None);
+    assert_eq!(
+        suppress_by_ignore_prefix("////hello", "//"),
+        Some("////hello")
+    );
+}
+
+#[test]
+fn bot_command_prefix_unit_test() {
+    let settings = crate::settings::GuildSettings::default();
+
+    assert_eq!(
+        suppress_by_bot_command_prefix("hello", &settings.bot_command_prefix),
+        Some("hello")
+    );
+    assert_eq!(
+        suppress_by_bot_command_prefix("!hello", &settings.bot_command_prefix),
+        None
+    );
+    assert_eq!(suppress_by_bot_command_prefix("!hello", ""), Some("!hello"));
+}
+
+#[test]
+fn uri_unit_test() {
+    let settings = crate::settings::GuildSettings::default();
+
+    assert_eq!(
+        replace_uri("hello", &settings.uri_replacement, None),
+        "hello"
+    );
+    assert_eq!(
+        replace_uri(
+            "ms-settings:privacy-microphone",
+            &settings.uri_replacement,
+            None
+        ),
+        "。URI省略。"
+    );
+    assert_eq!(
+        replace_uri(
+            "some.strange-protocol+ver2:pathpathpath",
+            &settings.uri_replacement,
+            None
+        ),
+        "。URI省略。"
+    );
+    assert_eq!(
+        replace_uri("||https://example.com||", &settings.uri_replacement, None),
+        "||。URI省略。||"
+    );
+    assert_eq!(
+        replace_uri("`https://example.com`", &settings.uri_replacement, None),
+        "`。URI省略。`"
+    );
+    assert_eq!(
+        replace_uri("https://example.com/?a|b", &settings.uri_replacement, None),
+        "。URI省略。"
+    );
+    assert_eq!(
+        replace_uri("20:40に秋葉原にて待つ", &settings.uri_replacement, None),
+        "20:40に秋葉原にて待つ"
+    );
+    assert_eq!(
+        replace_uri(
+            "abc,def://nyan.com:22/mofu",
+            &settings.uri_replacement,
+            None
+        ),
+        "abc,。URI省略。"
+    );
+    assert_eq!(
+        replace_uri(
+            "そこから ms-settings:privacy-microphone を開いて",
+            &settings.uri_replacement,
+            None
+        ),
+        "そこから 。URI省略。 を開いて"
+    );
+    assert_eq!(
+        replace_uri(
+            "そこから http://metaba.su を開いて",
+            &settings.uri_replacement,
+            None
+        ),
+        "そこから 。URI省略。 を開いて"
+    );
+    assert_eq!(replace_uri("see http://a.example", "$0", None), "see $0");
+
+    let domain = Some(settings.domain_replacement.as_str());
+    let replace = |mes| replace_uri(mes, &settings.uri_replacement, domain);
+    assert_eq!(
+        replace("https://www.youtube.com/watch?v=xxxx"),
+        "。ユーチューブのリンク。"
+    );
+    assert_eq!(
+        replace("見て https://m.youtube.com/watch?v=xxxx"),
+        "見て 。ユーチューブのリンク。"
+    );
+    assert_eq!(
+        replace("https://github.com/user/repo"),
+        "。github.comのリンク。"
+    );
+    assert_eq!(
+        replace("http://notyoutube.com"),
+        "。notyoutube.comのリンク。"
+    );
+    assert_eq!(replace("ms-settings:privacy-microphone"), "。URI省略。");
+    assert_eq!(replace("20:40に秋葉原にて待つ"), "20:40に秋葉原にて待つ");
+    assert_eq!(
+        replace_uri("https://x.com/a", "", Some("$0 {domain}")),
+        "$0 エックス"
+    );
+}
+
+#[test]
+fn emoji_unit_test() {
+    let none = HashMap::new();
+
+    assert_eq!(replace_emoji("hello!", &none, false), "hello!");
+    assert_eq!(replace_emoji("hello:emoji:!", &none, false), "hello!");
+    assert_eq!(
+        replace_external_emoji("hello<:emoji:012345678901234567>!", &none, false),
+        "hello!"
+    );
+
+    let readings = HashMap::from([("kusa".to_string(), "草".to_string())]);
+
+    assert_eq!(
+        replace_external_emoji("それは<:kusa:012345678901234567>", &readings, false),
+        "それは草"
+    );
+    assert_eq!(
+        replace_external_emoji("<a:party_parrot:012345678901234567>", &readings, true),
+        "party parrot"
+    );
+    assert_eq!(
+        replace_emoji(":kusa::party_time:", &readings, true),
+        "草party time"
+    );
+    assert_eq!(replace_emoji(":kusa::party_time:", &readings, false), "草");
+
+    let readings = HashMap::from([("dollar".to_string(), "$1".to_string())]);
+    assert_eq!(replace_emoji(":dollar:", &readings, false), "$1");
+}
+
+#[test]
+fn unicode_emoji_unit_test() {
+    assert_eq!(replace_unicode_emoji("hello"), "hello");
+    assert_eq!(replace_unicode_emoji("🍣"), "すし");
+    assert_eq!(replace_unicode_emoji("a🍣b🍣"), "aすしbすし");
+    assert_eq!(replace_unicode_emoji("ガンバ"), "ガンバ");
+    assert_eq!(replace_unicode_emoji("café"), "café");
+
+    // Variation selectors and skin tones
+    assert_eq!(replace_unicode_emoji("❤️"), "赤いハート");
+    assert_eq!(replace_unicode_emoji("👍🏽👍"), "サムズアップサムズアップ");
+
+    // Flags
+    assert_eq!(replace_unicode_emoji("🇯🇵"), "日本");
+    assert_eq!(replace_unicode_emoji("🇺🇳🇯🇵"), "国際連合日本");
+    assert_eq!(replace_unicode_emoji("🏳️‍🌈"), "レインボーフラッグ");
+
+    // Keycaps
+    assert_eq!(replace_unicode_emoji("1️⃣"), "1");
+    assert_eq!(replace_unicode_emoji("#⃣"), "#");
+
+    // ZWJ sequences
+    assert_eq!(replace_unicode_emoji("👩‍💻"), "女性技術者");
+    assert_eq!(replace_unicode_emoji("🏃‍♀️"), "走る女");
+    assert_eq!(replace_unicode_emoji("🏃🏻‍♀️"), "走る女");
+    assert_eq!(replace_unicode_emoji("👨‍👩‍👧‍👦"), "家族");
+    assert_eq!(replace_unicode_emoji("👩‍👦"), "家族");
+    assert_eq!(replace_unicode_emoji("👩‍❤️‍👨"), "カップルとハート");
+    assert_eq!(replace_unicode_emoji("🧑‍🦲"), "大人");
+    assert_eq!(replace_unicode_emoji("🧑‍🦲‍♂️"), replace_unicode_emoji("🧑‍🦲"));
+}
+
+/// Compares against replacing each entry in turn, as was done before.
+/// Run with `cargo test --release -- --ignored --nocapture unicode_emoji_benchmark`.
+#[test]
+#[ignore = "benchmark"]
+fn unicode_emoji_benchmark() {
+    use std::time::Instant;
+
+    let dictionary = crate::db::EmojiDB::load_dictionary();
+    let message = "今日は🍣を食べた😋 明日は🍜にしよう👍 ".repeat(50);
+
+    let naive = |mes: &str| {
+        let mut s = mes.to_string();
+        for (word, replacement) in &dictionary {
+            s = s.replace(word.as_str(), replacement.as_str());
+        }
+        s
+    };
+
+    let iterations = 20;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(naive(&message));
+    }
+    let naive_time = start.elapsed() / iterations;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(replace_unicode_emoji(&message));
+    }
+    let matcher_time = start.elapsed() / iterations;
+
+    println!(
+        "{} chars: String::replace {naive_time:?}, grapheme lookup {matcher_time:?} ({:.0}x)",
+        message.chars().count(),
+        naive_time.as_secs_f64() / matcher_time.as_secs_f64()
+    );
+}
+
+#[test]
+fn codeblock_unit_test() {
+    let settings = crate::settings::GuildSettings::default();
+
+    assert_eq!(
+        replace_codeblock("Codeblock ```Inline``` !", &settings.code_replacement),
+        "Codeblock 。コード省略。 !"
+    );
+    assert_eq!(
+        replace_codeblock(
+            "Codeblock\n```\nMultiline\n```\n!",
+            &settings.code_replacement
+        ),
+        "Codeblock\n。コード省略。\n!"
+    );
+}
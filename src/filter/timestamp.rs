@@ -0,0 +1,138 @@
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Timelike, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{FilterStage, StageContext};
+use crate::settings::{parse_utc_offset, DEFAULT_TIMEZONE};
+
+// regex crate's named capture
+#[allow(clippy::invalid_regex)]
+static TIMESTAMP_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<t:(?<secs>-?\d+)(?::(?<style>[tTdDfFR]))?>").unwrap());
+
+const WEEKDAYS: [&str; 7] = ["月", "火", "水", "木", "金", "土", "日"];
+
+/// Reads Discord timestamps `<t:secs:style>` in the guild's timezone.
+/// This must run before the emoji and URI stages, which would take `t:…:F>` apart.
+pub struct Timestamp;
+
+impl FilterStage for Timestamp {
+    fn name(&self) -> &'static str {
+        "timestamp"
+    }
+
+    fn apply(&self, text: String, ctx: &StageContext) -> Option<String> {
+        // `/config set` rejects an invalid timezone, but one may have been stored by hand.
+        // Returning `None` here would silence the message altogether.
+        let offset = parse_utc_offset(&ctx.settings.timezone)
+            .or_else(|| parse_utc_offset(DEFAULT_TIMEZONE))
+            .unwrap();
+
+        Some(replace_timestamps(&text, Utc::now().with_timezone(&offset)))
+    }
+}
+
+fn replace_timestamps(mes: &str, now: DateTime<FixedOffset>) -> String {
+    TIMESTAMP_REGEX
+        .replace_all(mes, |caps: &regex::Captures| {
+            let style = caps
+                .name("style")
+                .map_or('f', |s| s.as_str().chars().next().unwrap());
+
+            caps["secs"]
+                .parse()
+                .ok()
+                .and_then(|secs| read_timestamp(secs, style, now))
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Reads a timestamp the way the Japanese client shows it, in words.
+/// `style` is one of `tTdDfFR`; an unknown style is read as `f`, the default.
+fn read_timestamp(secs: i64, style: char, now: DateTime<FixedOffset>) -> Option<String> {
+    let time = now.timezone().timestamp_opt(secs, 0).single()?;
+
+    let date = format!("{}年{}月{}日", time.year(), time.month(), time.day());
+    let weekday = WEEKDAYS[time.weekday().num_days_from_monday() as usize];
+    let clock = read_clock(&time);
+
+    Some(match style {
+        't' => clock,
+        'T' => format!("{clock}{}秒", time.second()),
+        'd' | 'D' => date,
+        'F' => format!("{date}{weekday}曜日 {clock}"),
+        'R' => read_relative(time.signed_duration_since(now).num_seconds()),
+        _ => format!("{date} {clock}"),
+    })
+}
+
+fn read_clock(time: &DateTime<FixedOffset>) -> String {
+    let (pm, hour) = time.hour12();
+    // `hour12` counts from 1 to 12, while 午前 and 午後 count from 0 to 11.
+    let hour = hour % 12;
+    let half = if pm { "午後" } else { "午前" };
+
+    match time.minute() {
+        0 => format!("{half}{hour}時"),
+        minute => format!("{half}{hour}時{minute}分"),
+    }
+}
+
+fn read_relative(secs: i64) -> String {
+    const UNITS: [(i64, &str); 6] = [
+        (365 * 24 * 60 * 60, "年"),
+        (30 * 24 * 60 * 60, "か月"),
+        (24 * 60 * 60, "日"),
+        (60 * 60, "時間"),
+        (60, "分"),
+        (1, "秒"),
+    ];
+
+    let suffix = if secs < 0 { "前" } else { "後" };
+    let secs = secs.abs();
+
+    match UNITS.iter().find(|(unit, _)| secs >= *unit) {
+        Some((unit, name)) => format!("{}{name}{suffix}", secs / unit),
+        None => "今".to_string(),
+    }
+}
+
+#[test]
+fn timestamp_unit_test() {
+    let jst = parse_utc_offset("+09:00").unwrap();
+    // 2021-04-21 06:20:30 JST, a Wednesday
+    let now = jst.timestamp_opt(1_618_953_630, 0).unwrap();
+    let read = |secs, style| read_timestamp(secs, style, now).unwrap();
+
+    assert_eq!(read(1_618_953_630, 't'), "午前6時20分");
+    assert_eq!(read(1_618_953_630, 'T'), "午前6時20分30秒");
+    assert_eq!(read(1_618_953_630, 'd'), "2021年4月21日");
+    assert_eq!(read(1_618_953_630, 'f'), "2021年4月21日 午前6時20分");
+    assert_eq!(read(1_618_953_630, 'F'), "2021年4月21日水曜日 午前6時20分");
+    assert_eq!(read(1_618_953_630, 'R'), "今");
+    assert_eq!(read(1_618_953_630 + 3 * 60 * 60, 'R'), "3時間後");
+    assert_eq!(read(1_618_953_630 - 2 * 24 * 60 * 60, 'R'), "2日前");
+    assert_eq!(read(1_618_953_630 - 400 * 24 * 60 * 60, 'R'), "1年前");
+    assert!(read_timestamp(i64::MAX, 'f', now).is_none());
+
+    // 2026-10-18 15:00:00 JST
+    assert_eq!(read(1_792_303_200, 'f'), "2026年10月18日 午後3時");
+    assert_eq!(read(1_792_303_200 - 15 * 60 * 60, 't'), "午前0時");
+    assert_eq!(read(1_792_303_200 - 3 * 60 * 60, 't'), "午後0時");
+
+    let utc = parse_utc_offset("UTC").unwrap();
+    assert_eq!(
+        read_timestamp(1_792_303_200, 'f', now.with_timezone(&utc)).unwrap(),
+        "2026年10月18日 午前6時"
+    );
+
+    assert_eq!(
+        replace_timestamps("<t:1792303200:F>に集合", now),
+        "2026年10月18日日曜日 午後3時に集合"
+    );
+    assert_eq!(
+        replace_timestamps("<t:1618964430:R>まで <t:99999999999999999999>", now),
+        "3時間後まで <t:99999999999999999999>"
+    );
+}
@@ -4,13 +4,20 @@ mod commands;
 mod config;
 mod db;
 mod filter;
+mod replacer;
+mod settings;
+mod shutdown;
 mod songbird_handler;
 mod sozai;
+mod tts;
+mod voice_state;
 mod voicevox;
 mod wavsource;
 
 use std::io::Cursor;
+use std::time::Duration;
 
+use bytes::Bytes;
 use reqwest::Url;
 use serenity::{
     async_trait,
@@ -18,19 +25,28 @@ use serenity::{
     model::{
         application::{Command, Interaction},
         channel::Message,
+        event::MessageUpdateEvent,
         gateway::Ready,
+        id::GuildId,
         prelude::GatewayIntents,
+        voice::VoiceState,
     },
+    prelude::Mutex,
 };
-use songbird::{input::HttpRequest, SerenityInit};
+use songbird::{input::Input, tracks::TrackHandle, Call, SerenityInit, Songbird};
 use tap::Tap;
 
 use crate::config::CONFIG;
-use crate::db::INMEMORY_DB;
 use crate::db::PERSISTENT_DB;
+use crate::db::{Utterance, INMEMORY_DB};
+use crate::settings::EditReadMode;
+use crate::tts::Voice;
+use crate::voicevox::model::SpeakerId;
 
 struct Bot {
     voicevox: voicevox::Client,
+    engines: tts::Engines,
+    sozai: sozai::Cache,
     prefix: String,
 }
 
@@ -45,6 +61,11 @@ impl EventHandler for Bot {
                 commands::skip::register(&self.prefix),
                 commands::speaker::register(&self.prefix),
                 commands::dict::register(&self.prefix),
+                commands::config::register(&self.prefix),
+                commands::link::register(&self.prefix),
+                commands::unlink::register(&self.prefix),
+                commands::yomi::register(&self.prefix),
+                commands::sozai::register(&self.prefix),
             ],
         )
         .await
@@ -53,50 +74,164 @@ impl EventHandler for Bot {
         println!("{} is connected!", ready.user.name);
     }
 
+    async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
+        voice_state::restore_sessions(&ctx).await;
+    }
+
     async fn message(&self, ctx: Context, msg: Message) {
-        let Some(content) = filter::filter(&ctx, &msg.clone()).await else {
+        if INMEMORY_DB.is_shutting_down() {
+            return;
+        }
+
+        let Some(speech) = filter::filter(&ctx, &msg.clone()).await else {
             return;
         };
-        if let Some(url) = INMEMORY_DB.get_sozai_url(&msg.content) {
-            let client = HttpRequest::new(reqwest::Client::new(), url.clone());
 
-            let manager = songbird::get(&ctx)
-                .await
-                .expect("Songbird is not initialized");
+        let manager = songbird::get(&ctx)
+            .await
+            .expect("Songbird is not initialized");
 
-            let handler = manager.get(msg.guild_id.unwrap()).unwrap();
+        let handler = manager.get(msg.guild_id.unwrap()).unwrap();
 
-            let track = handler.lock().await.enqueue_input(client.into()).await;
-            track.set_volume(0.3).unwrap();
-        } else {
-            let speaker = PERSISTENT_DB.get_speaker_id(msg.author.id);
+        if let Some(asset) = INMEMORY_DB.get_sozai(&msg.content) {
+            // Nothing but verified bytes is played, so a sozai which cannot be fetched or verified is skipped.
+            let input: Input = match self.sozai.get(&asset).await {
+                Ok(bytes) => bytes.into(),
+                Err(e) => {
+                    println!("Skipped the sozai {}: {e:?}", asset.url());
+                    return;
+                }
+            };
 
-            let manager = songbird::get(&ctx)
+            let volume = PERSISTENT_DB
+                .get_guild_settings(msg.guild_id.unwrap())
+                .sozai_volume;
+
+            let track = handler.lock().await.enqueue_input(input).await;
+            #[allow(clippy::cast_possible_truncation)]
+            track.set_volume(volume as f32).unwrap();
+        } else {
+            let Some(input) = self
+                .synthesize(&ctx, &msg, &speech.text, speech.voice.as_ref())
                 .await
-                .expect("Songbird is not initialized");
+            else {
+                return;
+            };
 
-            let handler = manager.get(msg.guild_id.unwrap()).unwrap();
+            let track = handler.lock().await.enqueue_input(input).await;
 
-            let Ok(wav) = self.voicevox.tts(&content, speaker).await else {
-                msg.reply(&ctx.http, "Error: Failed to synthesise a message")
-                    .await
-                    .unwrap();
+            INMEMORY_DB
+                .store_utterance(
+                    msg.id,
+                    Utterance {
+                        text: speech.text,
+                        track,
+                        correction: false,
+                    },
+                )
+                .await;
+        }
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        let Some(guild_id) = event.guild_id else {
+            return;
+        };
+
+        if INMEMORY_DB.is_shutting_down() {
+            return;
+        }
+
+        let mode = PERSISTENT_DB.get_guild_settings(guild_id).read_edits;
+
+        // An update without content is not an edit by the author (e.g. an embed has been resolved).
+        if mode == EditReadMode::Off || event.content.is_none() {
+            return;
+        }
+
+        // Messages which have not been spoken, or were spoken too long ago, are not corrected.
+        let Some(previous) = INMEMORY_DB.get_utterance(event.id).await else {
+            return;
+        };
+
+        let msg = if let Some(msg) = new {
+            msg
+        } else {
+            let Ok(msg) = event.channel_id.message(&ctx, event.id).await else {
                 return;
             };
 
-            handler
-                .lock()
-                .await
-                .enqueue_input(
-                    songbird::input::RawAdapter::new(
-                        wavsource::WavSource::new(&mut Cursor::new(wav)),
-                        48000,
-                        1,
-                    )
-                    .into(),
+            // Messages fetched over HTTP do not carry their guild.
+            msg.tap_mut(|m| m.guild_id = Some(guild_id))
+        };
+
+        let Some(filter::Speech {
+            text: content,
+            voice,
+        }) = filter::filter(&ctx, &msg).await
+        else {
+            return;
+        };
+
+        let manager = songbird::get(&ctx)
+            .await
+            .expect("Songbird is not initialized");
+
+        let Some(handler) = manager.get(guild_id) else {
+            return;
+        };
+
+        if previous.text == content {
+            return;
+        }
+
+        if let Some(track) = self
+            .replace_queued(&ctx, &msg, &handler, &previous, &content, voice.as_ref())
+            .await
+        {
+            INMEMORY_DB
+                .store_utterance(
+                    msg.id,
+                    Utterance {
+                        text: content,
+                        track,
+                        correction: previous.correction,
+                    },
                 )
                 .await;
+            return;
+        }
+
+        let Some(text) = filter::correction(&previous.text, &content, mode) else {
+            return;
         };
+
+        let Some(input) = self.synthesize(&ctx, &msg, &text, voice.as_ref()).await else {
+            return;
+        };
+
+        let track = handler.lock().await.enqueue_input(input).await;
+
+        INMEMORY_DB
+            .store_utterance(
+                msg.id,
+                Utterance {
+                    text: content,
+                    track,
+                    correction: true,
+                },
+            )
+            .await;
+    }
+
+    async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+        voice_state::handle(self, &ctx, old.as_ref(), &new).await;
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
@@ -108,18 +243,166 @@ impl EventHandler for Bot {
                 }
                 s if s == format!("{prefix}join") => commands::join::run(&ctx, command).await,
                 s if s == format!("{prefix}leave") => commands::leave::run(&ctx, command).await,
+                s if s == format!("{prefix}link") => commands::link::run(&ctx, command).await,
+                s if s == format!("{prefix}unlink") => commands::unlink::run(&ctx, command).await,
                 s if s == format!("{prefix}skip") => commands::skip::run(&ctx, command).await,
                 s if s == format!("{prefix}dict") => commands::dict::run(&ctx, command).await,
+                s if s == format!("{prefix}yomi") => commands::yomi::run(&ctx, command).await,
+                s if s == format!("{prefix}sozai") => commands::sozai::run(&ctx, command).await,
+                s if s == format!("{prefix}config") => {
+                    commands::config::run(&ctx, command, &self.voicevox).await;
+                }
                 _ => unreachable!("Unknown command: {}", command.data.name),
             },
             Interaction::Component(interaction) => {
                 commands::speaker::update(&ctx, interaction, &self.voicevox).await;
             }
+            Interaction::Autocomplete(interaction)
+                if interaction.data.name == format!("{prefix}config") =>
+            {
+                commands::config::autocomplete(&ctx, interaction).await;
+            }
             _ => {}
         }
     }
 }
 
+impl Bot {
+    /// Reads `text` in `voice`, or else in the voice of the author.
+    async fn synthesize(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        text: &str,
+        voice: Option<&Voice>,
+    ) -> Option<Input> {
+        let input = match voice {
+            Some(voice) => self.engines.tts(text, voice).await.ok().and_then(wav_input),
+            None => {
+                self.tts(text, PERSISTENT_DB.get_speaker_id(msg.author.id))
+                    .await
+            }
+        };
+
+        if input.is_none() {
+            msg.reply(&ctx.http, "Error: Failed to synthesise a message")
+                .await
+                .unwrap();
+        }
+
+        input
+    }
+
+    async fn tts(&self, text: &str, speaker: SpeakerId) -> Option<Input> {
+        let wav = self.voicevox.tts(text, speaker).await.ok()?;
+
+        wav_input(wav)
+    }
+
+    /// Re-synthesises an utterance which is still waiting in the queue, keeping its position.
+    /// Returns `None` if the utterance is already being played or has been played.
+    async fn replace_queued(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        handler: &Mutex<Call>,
+        previous: &Utterance,
+        content: &str,
+        voice: Option<&Voice>,
+    ) -> Option<TrackHandle> {
+        let uuid = previous.track.uuid();
+
+        // The head of the queue is the track being played.
+        if !handler
+            .lock()
+            .await
+            .queue()
+            .current_queue()
+            .iter()
+            .skip(1)
+            .any(|t| t.uuid() == uuid)
+        {
+            return None;
+        }
+
+        let text = if previous.correction {
+            filter::correction("", content, EditReadMode::Full)?
+        } else {
+            content.to_string()
+        };
+
+        let input = self.synthesize(ctx, msg, &text, voice).await?;
+
+        let mut call = handler.lock().await;
+        let track = call.enqueue_input(input).await;
+
+        let replaced = call.queue().modify_queue(|q| {
+            let old = q
+                .iter()
+                .position(|t| t.uuid() == uuid)
+                .filter(|&i| i != 0)?;
+            let new = q.iter().position(|t| t.uuid() == track.uuid())?;
+            let new = q.remove(new)?;
+            Some(std::mem::replace(&mut q[old], new))
+        });
+
+        if let Some(replaced) = replaced {
+            let _: Result<_, _> = replaced.stop();
+            Some(track)
+        } else {
+            // The original started playing while synthesising; fall back to a correction.
+            call.queue().modify_queue(|q| {
+                q.retain(|t| t.uuid() != track.uuid());
+            });
+            let _: Result<_, _> = track.stop();
+            None
+        }
+    }
+}
+
+/// Returns `None` for a WAV which cannot be read, so that the utterance is skipped.
+fn wav_input(wav: Bytes) -> Option<Input> {
+    let source = wavsource::WavSource::new(&mut Cursor::new(wav))
+        .map_err(|e| println!("Failed to read a WAV: {e:?}"))
+        .ok()?;
+
+    Some(songbird::input::RawAdapter::new(source, 48000, 1).into())
+}
+
+/// How often the sozai index is read again unless `SOZAI_REFRESH_SECS` is set.
+const SOZAI_REFRESH_SECS: u64 = 60 * 60;
+const SOZAI_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+const SOZAI_PREFETCH: usize = 50;
+
+/// Reads the sozai index now and then every `period`, so that new sozai are played without a restart.
+/// A failure keeps the last good index, and does not stop the bot even at startup.
+/// Popular sozai in the index are cached each time.
+async fn refresh_sozai_periodically(source: sozai::Source, period: Duration, cache: sozai::Cache) {
+    let prefetch = CONFIG.sozai_prefetch.unwrap_or(SOZAI_PREFETCH);
+
+    // `interval` panics with zero, which turns refreshes off instead.
+    if period.is_zero() {
+        refresh_sozai(&source).await;
+        return cache.prefetch(&INMEMORY_DB.sozai_assets(), prefetch).await;
+    }
+
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        refresh_sozai(&source).await;
+        cache.prefetch(&INMEMORY_DB.sozai_assets(), prefetch).await;
+    }
+}
+
+async fn refresh_sozai(source: &sozai::Source) {
+    match INMEMORY_DB.refresh_sozai(source).await {
+        Ok(changes) if !changes.is_empty() => println!("Refreshed the sozai index: {changes}"),
+        Ok(_) => {}
+        Err(e) => println!("Failed to refresh the sozai index: {e:?}"),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
@@ -144,26 +427,49 @@ async fn main() {
         }
     });
 
+    let songbird = Songbird::serenity();
+
+    let voicevox = voicevox::Client::new(
+        Url::parse(&CONFIG.voicevox_host).unwrap(),
+        reqwest::Client::builder()
+            .default_headers(default_header)
+            .build()
+            .unwrap(),
+    )
+    .await;
+
+    let mut engines = tts::Engines::new(voicevox.clone());
+
+    for (name, url) in tts::parse_engines(CONFIG.tts_engines.as_deref().unwrap_or_default()) {
+        engines.register(&name, tts::HttpEngine::new(reqwest::Client::new(), url));
+    }
+
+    let sozai_cache = sozai::Cache::new(
+        CONFIG
+            .sozai_cache_dir
+            .clone()
+            .unwrap_or_else(|| CONFIG.persistent_path.with_file_name("sozai")),
+        CONFIG.sozai_cache_bytes.unwrap_or(SOZAI_CACHE_BYTES),
+    );
+
     let mut client = Client::builder(&CONFIG.discord_token, intents)
         .event_handler(Bot {
-            voicevox: voicevox::Client::new(
-                Url::parse(&CONFIG.voicevox_host).unwrap(),
-                reqwest::Client::builder()
-                    .default_headers(default_header)
-                    .build()
-                    .unwrap(),
-            )
-            .await,
+            voicevox,
+            engines,
+            sozai: sozai_cache.clone(),
             prefix: CONFIG.command_prefix.clone().unwrap_or_default(),
         })
-        .register_songbird()
+        .register_songbird_with(songbird.clone())
         .await
         .expect("Failed to create client");
 
-    INMEMORY_DB
-        .init_sozai_map(&CONFIG.sozai_index_url)
-        .await
-        .unwrap();
+    let shard_manager = client.shard_manager.clone();
+
+    tokio::spawn(refresh_sozai_periodically(
+        sozai::Source::parse(&CONFIG.sozai_index_url),
+        Duration::from_secs(CONFIG.sozai_refresh_secs.unwrap_or(SOZAI_REFRESH_SECS)),
+        sozai_cache.clone(),
+    ));
 
     tokio::spawn(async move {
         let _: Result<_, _> = client
@@ -172,9 +478,15 @@ async fn main() {
             .map_err(|why| println!("Client ended: {why:?}"));
     });
 
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to wait Ctrl+C");
+    shutdown::signal().await;
+
+    println!("Received a signal, shutting down.");
 
-    println!("Received Ctrl+C, shutting down.");
+    shutdown::run(
+        &songbird,
+        &shard_manager,
+        &sozai_cache,
+        CONFIG.shutdown_timeout_secs.map(Duration::from_secs),
+    )
+    .await;
 }
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+
+/// Replaces every word of a dictionary in a single pass.
+/// Where words overlap, the longest one starting first wins.
+pub struct Replacer {
+    matcher: AhoCorasick,
+    replacements: Vec<String>,
+}
+
+impl Replacer {
+    pub fn new(dictionary: &HashMap<String, String>) -> Self {
+        // An empty word would match between every pair of characters.
+        let (words, replacements): (Vec<&str>, Vec<String>) = dictionary
+            .iter()
+            .filter(|(word, _)| !word.is_empty())
+            .map(|(word, replacement)| (word.as_str(), replacement.clone()))
+            .unzip();
+
+        let matcher = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(words)
+            .expect("Failed to build the dictionary matcher");
+
+        Self {
+            matcher,
+            replacements,
+        }
+    }
+
+    pub fn replace_all(&self, text: &str) -> String {
+        self.matcher.replace_all(text, &self.replacements)
+    }
+
+    /// Applies `f` to the text between the words, leaving the words as they are for `replace_all`.
+    pub fn map_unmatched(&self, text: &str, mut f: impl FnMut(&str) -> String) -> String {
+        let mut ret = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for m in self.matcher.find_iter(text) {
+            ret.push_str(&f(&text[last..m.start()]));
+            ret.push_str(&text[m.range()]);
+            last = m.end();
+        }
+
+        ret.push_str(&f(&text[last..]));
+        ret
+    }
+}
+
+#[test]
+fn replacer_unit_test() {
+    let dictionary = HashMap::from([
+        ("a".to_string(), "x".to_string()),
+        ("ab".to_string(), "y".to_string()),
+        ("b".to_string(), "a".to_string()),
+        (String::new(), "never".to_string()),
+    ]);
+    let replacer = Replacer::new(&dictionary);
+
+    assert_eq!(replacer.replace_all(""), "");
+    assert_eq!(replacer.replace_all("cd"), "cd");
+    assert_eq!(replacer.replace_all("abba"), "yax");
+    assert_eq!(Replacer::new(&HashMap::new()).replace_all("ab"), "ab");
+
+    let upper = |s: &str| s.to_uppercase();
+    assert_eq!(replacer.map_unmatched("cabdc", upper), "CabDC");
+    assert_eq!(replacer.map_unmatched("", upper), "");
+    assert_eq!(
+        Replacer::new(&HashMap::new()).map_unmatched("ab", upper),
+        "AB"
+    );
+}
@@ -0,0 +1,782 @@
+use std::collections::BTreeMap;
+
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serenity::model::id::ChannelId;
+
+use crate::filter::find_stage;
+use crate::voicevox::model::SpeakerId;
+
+/// Per-guild settings. Every field has a default, so that settings stored by older versions keep loading.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct GuildSettings {
+    pub read_edits: EditReadMode,
+    /// Seconds to stay in a voice channel without humans. `None` disables leaving automatically.
+    pub auto_leave_secs: Option<u64>,
+    pub auto_join: AutoJoin,
+    pub announcement: Announcement,
+    /// Also read threads whose parent is a linked text channel.
+    pub read_threads: bool,
+    pub join_channels: JoinChannels,
+    pub attachment_notification: AttachmentNotification,
+    pub uri_replacement: String,
+    /// Read HTTP(S) URLs as `domain_replacement` instead of `uri_replacement`.
+    pub read_domains: bool,
+    /// `{domain}` is replaced with the reading of the host.
+    pub domain_replacement: String,
+    pub code_replacement: String,
+    pub spoiler_replacement: String,
+    /// Read custom emoji without a reading in the emoji dictionary as their names, instead of skipping them.
+    pub read_emoji_names: bool,
+    /// Read English words in katakana, instead of letting the engine spell them out.
+    pub read_english: bool,
+    pub foreign_language: ForeignLanguage,
+    /// Read block quotes. Their markers are stripped either way.
+    pub read_quotes: bool,
+    pub reply_context: ReplyContext,
+    pub forward: Forward,
+    /// Messages starting with this are not read, unless it is doubled. Empty to disable.
+    pub ignore_prefix: String,
+    /// Messages starting with this are commands for other bots and not read. Empty to disable.
+    pub bot_command_prefix: String,
+    pub repetition: Repetition,
+    /// UTC offset to read timestamps in, e.g. `+09:00`.
+    pub timezone: String,
+    pub sozai_volume: f64,
+    /// Names of the filter stages to skip.
+    pub disabled_stages: Vec<String>,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            read_edits: EditReadMode::default(),
+            auto_leave_secs: Some(60),
+            auto_join: AutoJoin::default(),
+            announcement: Announcement::default(),
+            read_threads: false,
+            join_channels: JoinChannels::default(),
+            attachment_notification: AttachmentNotification::default(),
+            uri_replacement: "。URI省略。".to_string(),
+            read_domains: false,
+            domain_replacement: "。{domain}のリンク。".to_string(),
+            code_replacement: "。コード省略。".to_string(),
+            spoiler_replacement: "伏せ字".to_string(),
+            read_emoji_names: false,
+            read_english: false,
+            foreign_language: ForeignLanguage::default(),
+            read_quotes: true,
+            reply_context: ReplyContext::default(),
+            forward: Forward::default(),
+            ignore_prefix: ";".to_string(),
+            bot_command_prefix: "!".to_string(),
+            repetition: Repetition::default(),
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            sozai_volume: 0.3,
+            disabled_stages: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EditReadMode {
+    #[default]
+    Off,
+    Diff,
+    Full,
+}
+
+/// Joins `voice` when the first human connects to it.
+/// `text` defaults to the text chat built into `voice`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct AutoJoin {
+    pub voice: Option<ChannelId>,
+    pub text: Option<ChannelId>,
+}
+
+/// Reads out members joining or leaving the voice channel the bot is in.
+/// `{name}` in the templates is replaced with the name of the member.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Announcement {
+    pub enabled: bool,
+    pub join: String,
+    pub leave: String,
+    pub speaker: SpeakerId,
+}
+
+impl Default for Announcement {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            join: "{name}さんが入室しました".to_string(),
+            leave: "{name}さんが退室しました".to_string(),
+            speaker: 0,
+        }
+    }
+}
+
+/// Text channels `/join` links to the voice session.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinChannels {
+    /// The channel the command was run in.
+    #[default]
+    Command,
+    /// The text chat built into the voice channel.
+    Voice,
+    Both,
+}
+
+/// Wording of the notification of attachments, stickers, embeds and polls.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AttachmentNotification {
+    pub image: Wording,
+    pub video: Wording,
+    pub audio: Wording,
+    /// Attachments of any other type.
+    pub file: Wording,
+    pub sticker: Wording,
+    pub embed: Wording,
+    pub poll: Wording,
+    /// Joins the wordings of different types.
+    pub separator: String,
+    /// Appended to the message body. `{attachments}` is replaced with the joined wordings.
+    pub attached: String,
+    /// Used if the message has no body.
+    pub sent: String,
+}
+
+/// `one` is used if the message has nothing else to notify; otherwise `many`, with `{count}` replaced with the number.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Wording {
+    pub one: String,
+    pub many: String,
+}
+
+fn wording(one: &str, many: &str) -> Wording {
+    Wording {
+        one: one.to_string(),
+        many: many.to_string(),
+    }
+}
+
+impl Default for AttachmentNotification {
+    fn default() -> Self {
+        Self {
+            image: wording("画像", "画像{count}枚"),
+            video: wording("動画", "動画{count}件"),
+            audio: wording("音声", "音声{count}件"),
+            file: wording("ファイル", "ファイル{count}件"),
+            sticker: wording("スタンプ", "スタンプ{count}個"),
+            embed: wording("埋め込み", "埋め込み{count}件"),
+            poll: wording("投票", "投票{count}件"),
+            separator: "と".to_string(),
+            attached: "{attachments}添付".to_string(),
+            sent: "{attachments}が送信されました".to_string(),
+        }
+    }
+}
+
+/// Prefixes replies with who is replied to.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReplyContext {
+    pub enabled: bool,
+    /// `{name}` is replaced with the name of the author of the replied message.
+    pub template: String,
+}
+
+impl Default for ReplyContext {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "{name}さんへの返信、".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Forward {
+    /// `{body}` is replaced with the forwarded message.
+    pub template: String,
+    /// Used if the forwarded message cannot be fetched.
+    pub unavailable: String,
+}
+
+impl Default for Forward {
+    fn default() -> Self {
+        Self {
+            template: "転送、{body}".to_string(),
+            unavailable: "転送されたメッセージ".to_string(),
+        }
+    }
+}
+
+/// Shortens runs of a repeated character or token, which would take forever to read.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Repetition {
+    /// Runs longer than this many repetitions are cut down to it. `None` disables shortening.
+    pub limit: Option<u64>,
+    /// Net slang read as a whole, e.g. `www` as `わら`.
+    /// A key made of one repeated character matches runs of at least its length,
+    /// or a single character right after Japanese.
+    pub slang: BTreeMap<String, String>,
+}
+
+impl Default for Repetition {
+    fn default() -> Self {
+        Self {
+            limit: Some(3),
+            slang: [
+                ("ww", "わら"),
+                ("ｗｗ", "わら"),
+                ("888", "パチパチ"),
+                ("８８８", "パチパチ"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        }
+    }
+}
+
+/// How messages in languages other than Japanese are read.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ForeignLanguage {
+    pub mode: ForeignLanguageMode,
+    /// Read instead of the message in `replace` mode, and in `route` mode if the language has no voice.
+    pub replacement: String,
+    /// `engine:voice` by language code, e.g. `en` to `coqui:p225`.
+    pub voices: BTreeMap<String, String>,
+}
+
+impl Default for ForeignLanguage {
+    fn default() -> Self {
+        Self {
+            mode: ForeignLanguageMode::default(),
+            replacement: "外国語のメッセージ".to_string(),
+            voices: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForeignLanguageMode {
+    /// Read in the voice of the author like any other message.
+    #[default]
+    Read,
+    Skip,
+    Replace,
+    /// Read in the voice configured for the language.
+    Route,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Bool,
+    /// An integer of at least `min`, or `none`.
+    OptionalInteger {
+        min: u64,
+    },
+    /// A number from `min` to `max`.
+    Number {
+        min: f64,
+        max: f64,
+    },
+    Text,
+    /// A channel mention or ID, or `none`.
+    Channel,
+    Speaker,
+    Choice(&'static [&'static str]),
+    /// Comma-separated names of filter stages, or `none`.
+    Stages,
+    /// Comma-separated `key=value` pairs, or `none`.
+    Table,
+    /// `UTC` or an offset from it, e.g. `+09:00`.
+    UtcOffset,
+}
+
+pub struct Key {
+    /// Dot-separated path of the field in [`GuildSettings`].
+    pub name: &'static str,
+    pub kind: Kind,
+    pub description: &'static str,
+}
+
+const fn key(name: &'static str, kind: Kind, description: &'static str) -> Key {
+    Key {
+        name,
+        kind,
+        description,
+    }
+}
+
+pub const KEYS: &[Key] = &[
+    key(
+        "read_edits",
+        Kind::Choice(&["off", "diff", "full"]),
+        "How edited messages are read",
+    ),
+    key(
+        "auto_leave_secs",
+        Kind::OptionalInteger { min: 0 },
+        "Seconds to stay without humans in the voice channel",
+    ),
+    key(
+        "auto_join.voice",
+        Kind::Channel,
+        "Voice channel to join automatically",
+    ),
+    key(
+        "auto_join.text",
+        Kind::Channel,
+        "Text channel to read on joining automatically",
+    ),
+    key(
+        "announcement.enabled",
+        Kind::Bool,
+        "Read out members joining or leaving",
+    ),
+    key(
+        "announcement.join",
+        Kind::Text,
+        "Join message; {name} is the member",
+    ),
+    key(
+        "announcement.leave",
+        Kind::Text,
+        "Leave message; {name} is the member",
+    ),
+    key(
+        "announcement.speaker",
+        Kind::Speaker,
+        "Style ID to announce with",
+    ),
+    key(
+        "read_threads",
+        Kind::Bool,
+        "Read threads under linked channels",
+    ),
+    key(
+        "join_channels",
+        Kind::Choice(&["command", "voice", "both"]),
+        "Text channels linked by join",
+    ),
+    key(
+        "attachment_notification.image.one",
+        Kind::Text,
+        "Just an image",
+    ),
+    key(
+        "attachment_notification.image.many",
+        Kind::Text,
+        "Count of images; {count} is the number",
+    ),
+    key(
+        "attachment_notification.video.one",
+        Kind::Text,
+        "Just a video",
+    ),
+    key(
+        "attachment_notification.video.many",
+        Kind::Text,
+        "Count of videos; {count} is the number",
+    ),
+    key(
+        "attachment_notification.audio.one",
+        Kind::Text,
+        "Just an audio file",
+    ),
+    key(
+        "attachment_notification.audio.many",
+        Kind::Text,
+        "Count of audio files; {count} is the number",
+    ),
+    key(
+        "attachment_notification.file.one",
+        Kind::Text,
+        "Just another file",
+    ),
+    key(
+        "attachment_notification.file.many",
+        Kind::Text,
+        "Count of other files; {count} is the number",
+    ),
+    key(
+        "attachment_notification.sticker.one",
+        Kind::Text,
+        "Just a sticker",
+    ),
+    key(
+        "attachment_notification.sticker.many",
+        Kind::Text,
+        "Count of stickers; {count} is the number",
+    ),
+    key(
+        "attachment_notification.embed.one",
+        Kind::Text,
+        "Just an embed",
+    ),
+    key(
+        "attachment_notification.embed.many",
+        Kind::Text,
+        "Count of embeds; {count} is the number",
+    ),
+    key(
+        "attachment_notification.poll.one",
+        Kind::Text,
+        "Just a poll",
+    ),
+    key(
+        "attachment_notification.poll.many",
+        Kind::Text,
+        "Count of polls; {count} is the number",
+    ),
+    key(
+        "attachment_notification.separator",
+        Kind::Text,
+        "Joins the types of attachments",
+    ),
+    key(
+        "attachment_notification.attached",
+        Kind::Text,
+        "Attachments with text; {attachments} is the attachments",
+    ),
+    key(
+        "attachment_notification.sent",
+        Kind::Text,
+        "Attachments only; {attachments} is the attachments",
+    ),
+    key("uri_replacement", Kind::Text, "Read instead of a URI"),
+    key("read_domains", Kind::Bool, "Read the domain of a URL"),
+    key(
+        "domain_replacement",
+        Kind::Text,
+        "Read instead of a URL; {domain} is the domain",
+    ),
+    key(
+        "code_replacement",
+        Kind::Text,
+        "Read instead of a code block",
+    ),
+    key(
+        "spoiler_replacement",
+        Kind::Text,
+        "Read instead of a spoiler",
+    ),
+    key("read_quotes", Kind::Bool, "Read block quotes"),
+    key(
+        "reply_context.enabled",
+        Kind::Bool,
+        "Read who a reply is to",
+    ),
+    key(
+        "reply_context.template",
+        Kind::Text,
+        "Prefix of replies; {name} is the replied member",
+    ),
+    key(
+        "forward.template",
+        Kind::Text,
+        "Forwarded messages; {body} is the message",
+    ),
+    key(
+        "forward.unavailable",
+        Kind::Text,
+        "Forwarded messages which cannot be fetched",
+    ),
+    key("read_emoji_names", Kind::Bool, "Read names of custom emoji"),
+    key("read_english", Kind::Bool, "Read English words in katakana"),
+    key(
+        "foreign_language.mode",
+        Kind::Choice(&["read", "skip", "replace", "route"]),
+        "How messages not in Japanese are read",
+    ),
+    key(
+        "foreign_language.replacement",
+        Kind::Text,
+        "Read instead of a message not in Japanese",
+    ),
+    key(
+        "foreign_language.voices",
+        Kind::Table,
+        "Voices by language like en=coqui:p225,ko=voicevox:3",
+    ),
+    key(
+        "ignore_prefix",
+        Kind::Text,
+        "Messages starting with this are not read",
+    ),
+    key(
+        "bot_command_prefix",
+        Kind::Text,
+        "Prefix of commands for other bots",
+    ),
+    key(
+        "repetition.limit",
+        Kind::OptionalInteger { min: 1 },
+        "Repetitions to keep of a repeated character or token",
+    ),
+    key(
+        "repetition.slang",
+        Kind::Table,
+        "Readings of repeated slang like ww=わら,888=パチパチ",
+    ),
+    key(
+        "timezone",
+        Kind::UtcOffset,
+        "UTC offset to read timestamps in, e.g. +09:00",
+    ),
+    key(
+        "sozai_volume",
+        Kind::Number { min: 0.0, max: 2.0 },
+        "Volume of sound effects, from 0 to 2",
+    ),
+    key(
+        "disabled_stages",
+        Kind::Stages,
+        "Filter stages to skip, comma-separated",
+    ),
+];
+
+pub fn find_key(name: &str) -> Option<&'static Key> {
+    KEYS.iter().find(|k| k.name == name)
+}
+
+fn pointer(key: &Key) -> String {
+    format!("/{}", key.name.replace('.', "/"))
+}
+
+impl GuildSettings {
+    pub fn get(&self, key: &Key) -> String {
+        let json = serde_json::to_value(self).unwrap();
+
+        match json.pointer(&pointer(key)) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => "none".to_string(),
+            Some(Value::Array(a)) if a.is_empty() => "none".to_string(),
+            Some(Value::Array(a)) => a
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(Value::Object(o)) if o.is_empty() => "none".to_string(),
+            Some(Value::Object(o)) => o
+                .iter()
+                .map(|(k, v)| format!("{k}={}", v.as_str().unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .join(","),
+            Some(v) => v.to_string(),
+        }
+    }
+
+    pub fn set(&mut self, key: &Key, value: &str) -> Result<(), String> {
+        let value =
+            parse(key.kind, value).ok_or_else(|| format!("Invalid value for {}", key.name))?;
+
+        self.replace(key, value)
+    }
+
+    pub fn reset(&mut self, key: &Key) {
+        let default = serde_json::to_value(Self::default()).unwrap();
+
+        self.replace(key, default.pointer(&pointer(key)).unwrap().clone())
+            .expect("Default settings are invalid");
+    }
+
+    fn replace(&mut self, key: &Key, value: Value) -> Result<(), String> {
+        let mut json = serde_json::to_value(&*self).unwrap();
+
+        *json.pointer_mut(&pointer(key)).ok_or("Unknown key")? = value;
+        *self = serde_json::from_value(json).map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
+fn parse(kind: Kind, value: &str) -> Option<Value> {
+    let value = value.trim();
+    let none = matches!(value, "none" | "off");
+
+    match kind {
+        Kind::Bool => match value {
+            "true" | "on" | "yes" => Some(Value::Bool(true)),
+            "false" | "off" | "no" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Kind::OptionalInteger { .. } | Kind::Channel if none => Some(Value::Null),
+        Kind::OptionalInteger { min } => value
+            .parse::<u64>()
+            .ok()
+            .filter(|v| *v >= min)
+            .map(Value::from),
+        Kind::Speaker => value.parse::<u64>().ok().map(Value::from),
+        Kind::Number { min, max } => value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| (min..=max).contains(v))
+            .map(Value::from),
+        Kind::Text => Some(Value::String(value.to_string())),
+        Kind::Channel => value
+            .trim_start_matches("<#")
+            .trim_end_matches('>')
+            .parse::<u64>()
+            .ok()
+            .map(|id| Value::String(id.to_string())),
+        Kind::Choice(choices) => choices
+            .contains(&value)
+            .then(|| Value::String(value.to_string())),
+        Kind::Stages if none => Some(Value::Array(vec![])),
+        Kind::Stages => value
+            .split(',')
+            .map(str::trim)
+            .map(|name| find_stage(name).map(|s| Value::String(s.name().to_string())))
+            .collect::<Option<_>>()
+            .map(Value::Array),
+        Kind::UtcOffset => parse_utc_offset(value).map(|_| Value::String(value.to_string())),
+        Kind::Table if none => Some(Value::Object(Map::new())),
+        Kind::Table => value
+            .split(',')
+            .map(|pair| {
+                let (k, v) = pair.split_once('=')?;
+                let k = k.trim();
+                (!k.is_empty()).then(|| (k.to_string(), Value::String(v.trim().to_string())))
+            })
+            .collect::<Option<_>>()
+            .map(Value::Object),
+    }
+}
+
+/// Japan Standard Time, also used in place of a stored timezone which cannot be parsed.
+pub const DEFAULT_TIMEZONE: &str = "+09:00";
+
+/// Accepts `UTC`, `+09:00`, `-0530` and `+9`.
+pub fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, rest) = match value.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+
+    let (hours, minutes) = match rest.split_once(':') {
+        Some(hm) => hm,
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    FixedOffset::east_opt(sign * (hours * 60 + minutes) * 60)
+}
+
+#[test]
+fn settings_unit_test() {
+    let mut settings = GuildSettings::default();
+
+    for key in KEYS {
+        let value = settings.get(key);
+        settings.set(key, &value).unwrap();
+        assert_eq!(settings.get(key), value, "{}", key.name);
+    }
+
+    let key = find_key("read_edits").unwrap();
+    assert_eq!(settings.get(key), "off");
+    settings.set(key, "diff").unwrap();
+    assert_eq!(settings.read_edits, EditReadMode::Diff);
+    assert!(settings.set(key, "sometimes").is_err());
+    settings.reset(key);
+    assert_eq!(settings.read_edits, EditReadMode::Off);
+
+    let key = find_key("auto_leave_secs").unwrap();
+    settings.set(key, "off").unwrap();
+    assert_eq!(settings.auto_leave_secs, None);
+    settings.set(key, "30").unwrap();
+    assert_eq!(settings.auto_leave_secs, Some(30));
+    assert!(settings.set(key, "-1").is_err());
+
+    let key = find_key("auto_join.voice").unwrap();
+    settings.set(key, "<#1234>").unwrap();
+    assert_eq!(settings.auto_join.voice, Some(ChannelId::new(1234)));
+    settings.set(key, "none").unwrap();
+    assert_eq!(settings.auto_join.voice, None);
+
+    let key = find_key("announcement.join").unwrap();
+    settings.set(key, "{name}が来た").unwrap();
+    assert_eq!(settings.announcement.join, "{name}が来た");
+
+    let key = find_key("sozai_volume").unwrap();
+    assert_eq!(settings.get(key), "0.3");
+    settings.set(key, "0.5").unwrap();
+    assert!((settings.sozai_volume - 0.5).abs() < f64::EPSILON);
+    assert!(settings.set(key, "loud").is_err());
+    assert!(settings.set(key, "-0.5").is_err());
+    assert!(settings.set(key, "NaN").is_err());
+    assert!(settings.set(key, "1e10").is_err());
+
+    let key = find_key("disabled_stages").unwrap();
+    assert_eq!(settings.get(key), "none");
+    settings.set(key, "uri, codeblock").unwrap();
+    assert_eq!(settings.disabled_stages, ["uri", "codeblock"]);
+    assert_eq!(settings.get(key), "uri,codeblock");
+    assert!(settings.set(key, "uri,nonexistent").is_err());
+
+    let key = find_key("repetition.limit").unwrap();
+    settings.set(key, "1").unwrap();
+    assert_eq!(settings.repetition.limit, Some(1));
+    assert!(settings.set(key, "0").is_err());
+    settings.set(key, "none").unwrap();
+    assert_eq!(settings.repetition.limit, None);
+
+    let key = find_key("repetition.slang").unwrap();
+    settings.set(key, "w=笑, 888 = パチパチ").unwrap();
+    assert_eq!(settings.get(key), "888=パチパチ,w=笑");
+    assert_eq!(settings.repetition.slang["w"], "笑");
+    assert!(settings.set(key, "w").is_err());
+    settings.set(key, "none").unwrap();
+    assert!(settings.repetition.slang.is_empty());
+
+    let key = find_key("foreign_language.mode").unwrap();
+    settings.set(key, "route").unwrap();
+    assert_eq!(settings.foreign_language.mode, ForeignLanguageMode::Route);
+    assert!(settings.set(key, "translate").is_err());
+
+    let key = find_key("foreign_language.voices").unwrap();
+    settings.set(key, "en=coqui:p225").unwrap();
+    assert_eq!(settings.foreign_language.voices["en"], "coqui:p225");
+
+    let key = find_key("timezone").unwrap();
+    settings.set(key, "-05:30").unwrap();
+    assert_eq!(
+        parse_utc_offset(&settings.timezone),
+        FixedOffset::west_opt(5 * 60 * 60 + 30 * 60)
+    );
+    assert!(settings.set(key, "Asia/Tokyo").is_err());
+    assert!(settings.set(key, "+25:00").is_err());
+    assert_eq!(parse_utc_offset("UTC"), FixedOffset::east_opt(0));
+    assert_eq!(parse_utc_offset("+9"), FixedOffset::east_opt(9 * 60 * 60));
+    assert_eq!(parse_utc_offset("+0930"), FixedOffset::east_opt(570 * 60));
+    assert_eq!(parse_utc_offset("+09:60"), None);
+
+    assert!(find_key("nonexistent").is_none());
+}
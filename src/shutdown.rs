@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::gateway::ShardManager;
+use songbird::{tracks::TrackHandle, Songbird};
+
+use crate::db::{INMEMORY_DB, PERSISTENT_DB};
+use crate::sozai;
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM (`docker stop`).
+pub async fn signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        r = tokio::signal::ctrl_c() => r.expect("Failed to wait Ctrl+C"),
+        () = terminate => {},
+    }
+}
+
+/// Leaves every voice channel and disconnects from the gateway.
+/// If `grace` is given, utterances being played are allowed to finish within it.
+pub async fn run(
+    songbird: &Songbird,
+    shard_manager: &Arc<ShardManager>,
+    sozai_cache: &sozai::Cache,
+    grace: Option<Duration>,
+) {
+    INMEMORY_DB.start_shutdown();
+
+    let calls: Vec<_> = songbird.iter().collect();
+
+    let mut playing = vec![];
+
+    for (_, call) in &calls {
+        let call = call.lock().await;
+        let queue = call.queue();
+
+        if grace.is_some() {
+            // Only the head of the queue is being played.
+            queue.modify_queue(|q| {
+                for track in q.drain(1..) {
+                    let _: Result<_, _> = track.stop();
+                }
+            });
+            playing.extend(queue.current());
+        } else {
+            queue.stop();
+        }
+    }
+
+    if let Some(grace) = grace {
+        let _: Result<_, _> = tokio::time::timeout(
+            grace,
+            futures::future::join_all(playing.iter().map(wait_for_end)),
+        )
+        .await;
+    }
+
+    for (guild_id, _) in calls {
+        let _: Result<_, _> = songbird.remove(guild_id).await;
+    }
+
+    PERSISTENT_DB.flush();
+    sozai_cache.save_plays();
+
+    shard_manager.shutdown_all().await;
+}
+
+async fn wait_for_end(track: &TrackHandle) {
+    while let Ok(state) = track.get_info().await {
+        if state.playing.is_done() {
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use serenity::async_trait;
 use songbird::{Event, EventContext, EventHandler as VoiceEventHandler, Songbird};
 
-use crate::db::INMEMORY_DB;
+use crate::db::{INMEMORY_DB, PERSISTENT_DB};
 
 pub struct DriverDisconnectNotifier {
     pub songbird_manager: Arc<Songbird>,
@@ -16,11 +16,12 @@ impl VoiceEventHandler for DriverDisconnectNotifier {
             return None;
         };
 
-        if ctx.reason.is_some() {
+        if ctx.reason.is_some() || INMEMORY_DB.is_shutting_down() {
             return None;
         }
 
         INMEMORY_DB.destroy_instance(ctx.guild_id.0.into());
+        PERSISTENT_DB.remove_session(ctx.guild_id.0.into());
         self.songbird_manager.remove(ctx.guild_id).await.unwrap();
 
         None
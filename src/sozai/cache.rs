@@ -0,0 +1,275 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+
+use super::Asset;
+
+/// Sozai kept in memory, in bytes.
+const MEMORY_CAPACITY: u64 = 32 * 1024 * 1024;
+
+/// Play counts are kept in the cache directory, so that prefetching knows what is popular after a restart.
+const PLAYS_FILE: &str = "plays.json";
+
+/// Sozai downloaded once and checked against `Asset::hash`, the SHA-256 of the file in hex.
+/// They are kept in memory and in a directory, where files are named by their hashes.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    inner: Arc<InnerCache>,
+}
+
+#[derive(Debug)]
+struct InnerCache {
+    dir: PathBuf,
+    /// The directory is kept under this many bytes by removing the least recently played files.
+    capacity: u64,
+    client: reqwest::Client,
+    memory: moka::future::Cache<String, Bytes>,
+    /// How many times each asset has been played, by id.
+    plays: Mutex<HashMap<String, u64>>,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, capacity: u64) -> Self {
+        std::fs::create_dir_all(&dir).expect("Failed to create the sozai cache directory.");
+
+        let plays = std::fs::read(dir.join(PLAYS_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+
+        let memory = moka::future::Cache::builder()
+            .weigher(|_, bytes: &Bytes| u32::try_from(bytes.len()).unwrap_or(u32::MAX))
+            .max_capacity(MEMORY_CAPACITY)
+            .build();
+
+        Self {
+            inner: Arc::new(InnerCache {
+                dir,
+                capacity,
+                client: reqwest::Client::new(),
+                memory,
+                plays: Mutex::new(plays),
+            }),
+        }
+    }
+
+    /// Returns the content of `asset` to be played, downloading it unless it has been cached.
+    pub async fn get(&self, asset: &Asset) -> anyhow::Result<Bytes> {
+        *self
+            .inner
+            .plays
+            .lock()
+            .unwrap()
+            .entry(asset.id.clone())
+            .or_default() += 1;
+
+        self.load(asset).await
+    }
+
+    /// Caches the `count` most played of `assets` ahead of time.
+    /// Plays of assets which are no longer in `assets` are forgotten.
+    pub async fn prefetch(&self, assets: &[Arc<Asset>], count: usize) {
+        let popular = {
+            let mut plays = self.inner.plays.lock().unwrap();
+            let ids: HashSet<&str> = assets.iter().map(|a| a.id.as_str()).collect();
+            plays.retain(|id, _| ids.contains(id.as_str()));
+
+            let mut popular: Vec<_> = assets
+                .iter()
+                .filter_map(|a| plays.get(&a.id).map(|p| (*p, a.clone())))
+                .collect();
+            popular.sort_unstable_by_key(|(plays, _)| Reverse(*plays));
+
+            popular
+        };
+
+        self.save_plays();
+
+        for (_, asset) in popular.into_iter().take(count) {
+            if let Err(e) = self.load(&asset).await {
+                println!("Failed to prefetch {}: {e:?}", asset.url);
+            }
+        }
+    }
+
+    /// Called on shutdown too, since prefetching may never run again after startup.
+    pub fn save_plays(&self) {
+        let json = serde_json::to_vec(&*self.inner.plays.lock().unwrap()).unwrap();
+
+        if let Err(e) = std::fs::write(self.inner.dir.join(PLAYS_FILE), json) {
+            println!("Failed to save sozai plays: {e:?}");
+        }
+    }
+
+    async fn load(&self, asset: &Asset) -> anyhow::Result<Bytes> {
+        // Concurrent requests for the same asset wait for a single download.
+        self.inner
+            .memory
+            .try_get_with(
+                asset.hash.to_ascii_lowercase(),
+                self.read_or_download(asset),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("{e:?}"))
+    }
+
+    async fn read_or_download(&self, asset: &Asset) -> anyhow::Result<Bytes> {
+        anyhow::ensure!(
+            !asset.hash.is_empty() && asset.hash.chars().all(|c| c.is_ascii_hexdigit()),
+            "{} has an invalid hash {}",
+            asset.id,
+            asset.hash
+        );
+
+        let path = self.inner.dir.join(asset.hash.to_ascii_lowercase());
+
+        if let Ok(bytes) = tokio::fs::read(&path).await {
+            if verify(asset, &bytes) {
+                // Access times are often not recorded, so the modification time tells when it was last used.
+                let _: Result<_, _> = std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|f| f.set_modified(SystemTime::now()));
+
+                return Ok(bytes.into());
+            }
+
+            let _: Result<_, _> = tokio::fs::remove_file(&path).await;
+        }
+
+        let bytes = self
+            .inner
+            .client
+            .get(&asset.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        anyhow::ensure!(
+            verify(asset, &bytes),
+            "{} does not match its hash",
+            asset.url
+        );
+
+        // It can still be played from memory.
+        if let Err(e) = self.store(&path, &bytes).await {
+            println!("Failed to cache {}: {e:?}", asset.url);
+        }
+
+        Ok(bytes)
+    }
+
+    async fn store(&self, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        if bytes.len() as u64 > self.inner.capacity {
+            return Ok(());
+        }
+
+        // Renamed when complete, so that a half written file is never read.
+        let part = path.with_extension("part");
+        tokio::fs::write(&part, bytes).await?;
+        tokio::fs::rename(&part, path).await?;
+
+        let mut files = vec![];
+        let mut entries = tokio::fs::read_dir(&self.inner.dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+
+            if metadata.is_file() && entry.file_name() != PLAYS_FILE {
+                files.push(CachedFile {
+                    path: entry.path(),
+                    len: metadata.len(),
+                    modified: metadata.modified()?,
+                });
+            }
+        }
+
+        for file in to_evict(files, self.inner.capacity, path) {
+            tokio::fs::remove_file(file).await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn verify(asset: &Asset, bytes: &[u8]) -> bool {
+    format!("{:x}", Sha256::digest(bytes)).eq_ignore_ascii_case(&asset.hash)
+}
+
+struct CachedFile {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+/// The least recently used files to remove for the rest to fit in `capacity`, except `keep`.
+fn to_evict(mut files: Vec<CachedFile>, capacity: u64, keep: &Path) -> Vec<PathBuf> {
+    let mut total: u64 = files.iter().map(|f| f.len).sum();
+    files.sort_unstable_by_key(|f| f.modified);
+
+    files
+        .into_iter()
+        .filter(|f| f.path != keep)
+        .take_while(|f| {
+            let over = total > capacity;
+            total = total.saturating_sub(f.len);
+            over
+        })
+        .map(|f| f.path)
+        .collect()
+}
+
+#[test]
+fn cache_unit_test() {
+    use std::time::Duration;
+
+    let asset = Asset {
+        hash: "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824".to_string(),
+        id: "hello".to_string(),
+        names: vec![],
+        namespaces: vec![],
+        path: String::new(),
+        url: String::new(),
+    };
+
+    assert!(verify(&asset, b"hello"));
+    assert!(!verify(&asset, b"hello!"));
+
+    let file = |name: &str, len: u64, secs: u64| CachedFile {
+        path: PathBuf::from(name),
+        len,
+        modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+    };
+    let files = || {
+        vec![
+            file("new", 30, 3),
+            file("old", 30, 1),
+            file("middle", 30, 2),
+            file("just stored", 30, 0),
+        ]
+    };
+
+    assert_eq!(
+        to_evict(files(), 60, Path::new("just stored")),
+        vec![PathBuf::from("old"), PathBuf::from("middle")]
+    );
+    assert_eq!(
+        to_evict(files(), 120, Path::new("just stored")),
+        Vec::<PathBuf>::new()
+    );
+    assert_eq!(
+        to_evict(files(), 0, Path::new("just stored")),
+        vec![
+            PathBuf::from("old"),
+            PathBuf::from("middle"),
+            PathBuf::from("new")
+        ]
+    );
+}
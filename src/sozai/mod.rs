@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use reqwest::{header, StatusCode, Url};
+use serde::Deserialize;
+
+pub use self::cache::Cache;
+
+mod cache;
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    hash: String,
+    id: String,
+    names: Vec<String>,
+    namespaces: Vec<String>,
+    path: String,
+    url: String,
+}
+
+impl Asset {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Where the index is read from, either an URL or a file on this machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Remote(Url),
+    Local(PathBuf),
+}
+
+impl Source {
+    /// Anything which is not an http(s) URL is taken as a path. `file://` URLs are accepted too.
+    pub fn parse(s: &str) -> Self {
+        match Url::parse(s) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Self::Remote(url),
+            Ok(url) if url.scheme() == "file" => {
+                Self::Local(url.to_file_path().unwrap_or_else(|()| PathBuf::from(s)))
+            }
+            _ => Self::Local(PathBuf::from(s)),
+        }
+    }
+}
+
+/// How the assets differ between two versions of the index, compared by `Asset::hash`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl std::fmt::Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} modified",
+            self.added, self.removed, self.modified
+        )
+    }
+}
+
+/// The index of sozai, looked up by their names.
+#[derive(Debug, Default)]
+pub struct Index {
+    /// Sent back as `If-None-Match`, so that an unchanged remote index is not downloaded again.
+    etag: Option<String>,
+    by_id: HashMap<String, Arc<Asset>>,
+    by_name: HashMap<String, Arc<Asset>>,
+}
+
+impl Index {
+    fn new(assets: Vec<Asset>, etag: Option<String>) -> Self {
+        let mut index = Self {
+            etag,
+            ..Self::default()
+        };
+
+        for asset in assets.into_iter().map(Arc::new) {
+            for name in &asset.names {
+                index.by_name.insert(name.clone(), asset.clone());
+            }
+            index.by_id.insert(asset.id.clone(), asset);
+        }
+
+        index
+    }
+
+    /// Reads the index from `source`.
+    /// Returns `None` if the remote index still has `etag`.
+    pub async fn fetch(source: &Source, etag: Option<&str>) -> anyhow::Result<Option<Self>> {
+        match source {
+            Source::Remote(url) => {
+                let mut request = reqwest::Client::new().get(url.clone());
+
+                if let Some(etag) = etag {
+                    request = request.header(header::IF_NONE_MATCH, etag);
+                }
+
+                let res = request.send().await?.error_for_status()?;
+
+                if res.status() == StatusCode::NOT_MODIFIED {
+                    return Ok(None);
+                }
+
+                let etag = res
+                    .headers()
+                    .get(header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(ToOwned::to_owned);
+
+                Ok(Some(Self::new(res.json().await?, etag)))
+            }
+            Source::Local(path) => {
+                let json = tokio::fs::read(path).await?;
+
+                Ok(Some(Self::new(serde_json::from_slice(&json)?, None)))
+            }
+        }
+    }
+
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Asset>> {
+        self.by_name.get(name).cloned()
+    }
+
+    pub fn assets(&self) -> Vec<Arc<Asset>> {
+        self.by_id.values().cloned().collect()
+    }
+
+    pub fn changes(&self, new: &Self) -> Changes {
+        let mut changes = Changes {
+            removed: self
+                .by_id
+                .keys()
+                .filter(|id| !new.by_id.contains_key(*id))
+                .count(),
+            ..Changes::default()
+        };
+
+        for (id, asset) in &new.by_id {
+            match self.by_id.get(id) {
+                None => changes.added += 1,
+                Some(old) if old.hash != asset.hash => changes.modified += 1,
+                Some(_) => {}
+            }
+        }
+
+        changes
+    }
+}
+
+#[test]
+fn sozai_unit_test() {
+    let asset = |id: &str, hash: &str, names: &[&str]| Asset {
+        hash: hash.to_string(),
+        id: id.to_string(),
+        names: names.iter().map(ToString::to_string).collect(),
+        namespaces: vec![],
+        path: format!("{id}.mp3"),
+        url: format!("https://example.com/{id}.mp3"),
+    };
+
+    assert_eq!(
+        Source::parse("https://example.com/index.json"),
+        Source::Remote(Url::parse("https://example.com/index.json").unwrap())
+    );
+    assert_eq!(
+        Source::parse("/srv/sozai/index.json"),
+        Source::Local(PathBuf::from("/srv/sozai/index.json"))
+    );
+    assert_eq!(
+        Source::parse("file:///srv/sozai/index.json"),
+        Source::Local(PathBuf::from("/srv/sozai/index.json"))
+    );
+    assert_eq!(
+        Source::parse("index.json"),
+        Source::Local(PathBuf::from("index.json"))
+    );
+
+    let old = Index::new(
+        vec![
+            asset("a", "1", &["あ", "a"]),
+            asset("b", "2", &["b"]),
+            asset("c", "3", &["c"]),
+        ],
+        None,
+    );
+    let new = Index::new(
+        vec![
+            asset("a", "1", &["あ", "a"]),
+            asset("b", "9", &["b"]),
+            asset("d", "4", &["d"]),
+        ],
+        None,
+    );
+
+    assert_eq!(new.get("あ").unwrap().url(), "https://example.com/a.mp3");
+    assert!(new.get("c").is_none());
+    assert_eq!(new.assets().len(), 3);
+    assert_eq!(
+        old.changes(&new),
+        Changes {
+            added: 1,
+            removed: 1,
+            modified: 1,
+        }
+    );
+    assert!(old.changes(&old).is_empty());
+}
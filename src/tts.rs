@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use reqwest::Url;
+use serenity::async_trait;
+
+use crate::voicevox;
+
+/// Name under which the VOICEVOX engine is registered.
+pub const VOICEVOX: &str = "voicevox";
+
+/// A speech synthesiser producing WAV.
+#[async_trait]
+pub trait Engine: Send + Sync {
+    /// `voice` is whatever the engine names its voices with.
+    async fn tts(&self, text: &str, voice: &str) -> Result<Bytes, ()>;
+}
+
+#[async_trait]
+impl Engine for voicevox::Client {
+    async fn tts(&self, text: &str, voice: &str) -> Result<Bytes, ()> {
+        let speaker = voice.parse().map_err(|_| ())?;
+
+        voicevox::Client::tts(self, text, speaker).await
+    }
+}
+
+/// An engine taking the text and the voice in the query of a GET request, like the server of Coqui TTS.
+/// `{text}` and `{voice}` in the values of the query are replaced with them.
+pub struct HttpEngine {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl HttpEngine {
+    pub fn new(client: reqwest::Client, url: Url) -> Self {
+        Self { client, url }
+    }
+
+    fn url(&self, text: &str, voice: &str) -> Url {
+        let pairs: Vec<(String, String)> = self
+            .url
+            .query_pairs()
+            .map(|(key, value)| {
+                let value = value.replace("{text}", text).replace("{voice}", voice);
+                (key.into_owned(), value)
+            })
+            .collect();
+
+        let mut url = self.url.clone();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+        url
+    }
+}
+
+#[async_trait]
+impl Engine for HttpEngine {
+    async fn tts(&self, text: &str, voice: &str) -> Result<Bytes, ()> {
+        self.client
+            .get(self.url(text, voice))
+            .send()
+            .await
+            .map_err(|_| ())?
+            .error_for_status()
+            .map_err(|_| ())?
+            .bytes()
+            .await
+            .map_err(|_| ())
+    }
+}
+
+/// The engines messages can be routed to, by name.
+#[derive(Clone)]
+pub struct Engines {
+    engines: HashMap<String, Arc<dyn Engine>>,
+}
+
+impl Engines {
+    pub fn new(voicevox: voicevox::Client) -> Self {
+        Self {
+            engines: HashMap::from([(VOICEVOX.to_string(), Arc::new(voicevox) as Arc<dyn Engine>)]),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, engine: impl Engine + 'static) {
+        self.engines.insert(name.to_string(), Arc::new(engine));
+    }
+
+    pub async fn tts(&self, text: &str, voice: &Voice) -> Result<Bytes, ()> {
+        let engine = self.engines.get(&voice.engine).ok_or(())?;
+
+        engine.tts(text, &voice.name).await
+    }
+}
+
+/// A voice of an engine, written as `engine:voice`, e.g. `voicevox:3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Voice {
+    pub engine: String,
+    pub name: String,
+}
+
+impl Voice {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (engine, name) = s.split_once(':')?;
+
+        (!engine.is_empty() && !name.is_empty()).then(|| Self {
+            engine: engine.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Parses `name=url` pairs separated by `;`, since URLs may well contain commas.
+pub fn parse_engines(s: &str) -> Vec<(String, Url)> {
+    s.split(';')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            let (name, url) = s
+                .split_once('=')
+                .unwrap_or_else(|| panic!("No URL is given for the engine {s}"));
+            let url = Url::parse(url.trim())
+                .unwrap_or_else(|_| panic!("Invalid URL for the engine {name}"));
+
+            (name.trim().to_string(), url)
+        })
+        .collect()
+}
+
+#[test]
+fn tts_unit_test() {
+    assert_eq!(
+        Voice::parse("coqui:p225"),
+        Some(Voice {
+            engine: "coqui".to_string(),
+            name: "p225".to_string(),
+        })
+    );
+    assert_eq!(Voice::parse("voicevox:"), None);
+    assert_eq!(Voice::parse("p225"), None);
+
+    let engines = parse_engines(
+        "coqui=http://localhost:5002/api/tts?text={text}&speaker_id={voice}; other = http://a/b ;",
+    );
+    assert_eq!(engines.len(), 2);
+    assert_eq!(engines[0].0, "coqui");
+    assert_eq!(engines[1].0, "other");
+
+    let engine = HttpEngine::new(reqwest::Client::new(), engines[0].1.clone());
+    assert_eq!(
+        engine.url("a&b c", "p225").as_str(),
+        "http://localhost:5002/api/tts?text=a%26b+c&speaker_id=p225"
+    );
+}
@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use serenity::{
+    client::Context,
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        prelude::Mentionable,
+        voice::VoiceState,
+    },
+};
+
+use crate::commands::join;
+use crate::db::{INMEMORY_DB, PERSISTENT_DB};
+use crate::settings::Announcement;
+use crate::Bot;
+
+pub async fn handle(bot: &Bot, ctx: &Context, old: Option<&VoiceState>, new: &VoiceState) {
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+
+    if INMEMORY_DB.is_shutting_down() {
+        return;
+    }
+
+    let settings = PERSISTENT_DB.get_guild_settings(guild_id);
+
+    if let Some(voice) = settings.auto_join.voice {
+        // Mute, deafen, streaming etc. also update the voice state, without anyone joining.
+        let joined = old.and_then(|o| o.channel_id) != Some(voice) && new.channel_id == Some(voice);
+
+        if joined && !is_bot(ctx, guild_id, new.user_id) {
+            let text = settings.auto_join.text.unwrap_or(voice);
+            join_automatically(ctx, guild_id, voice, text).await;
+        }
+    }
+
+    if settings.announcement.enabled {
+        announce(bot, ctx, guild_id, &settings.announcement, old, new).await;
+    }
+
+    // Every change starts the wait over, and someone coming back cancels it.
+    if let Some(secs) = settings.auto_leave_secs {
+        schedule_leave(ctx.clone(), guild_id, Duration::from_secs(secs));
+    }
+}
+
+async fn join_automatically(ctx: &Context, guild_id: GuildId, voice: ChannelId, text: ChannelId) {
+    if current_channel(ctx, guild_id).await.is_some() {
+        return;
+    }
+
+    if let Err(e) = join::connect(ctx, guild_id, voice, &[text]).await {
+        println!(
+            "Failed to join {guild_id} automatically: {}",
+            e.to_message()
+        );
+    }
+}
+
+async fn announce(
+    bot: &Bot,
+    ctx: &Context,
+    guild_id: GuildId,
+    announcement: &Announcement,
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+) {
+    let before = old.and_then(|o| o.channel_id);
+    let after = new.channel_id;
+
+    // Mute, deafen, streaming etc. also update the voice state.
+    if before == after || is_bot(ctx, guild_id, new.user_id) {
+        return;
+    }
+
+    let Some(channel) = current_channel(ctx, guild_id).await else {
+        return;
+    };
+
+    let template = if after == Some(channel) {
+        &announcement.join
+    } else if before == Some(channel) {
+        &announcement.leave
+    } else {
+        return;
+    };
+
+    let text = template.replace("{name}", &display_name(ctx, guild_id, new));
+
+    let Some(input) = bot.tts(&text, announcement.speaker).await else {
+        return;
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird is not initialized.");
+
+    if let Some(handler) = manager.get(guild_id) {
+        handler.lock().await.enqueue_input(input).await;
+    }
+}
+
+fn display_name(ctx: &Context, guild_id: GuildId, state: &VoiceState) -> String {
+    PERSISTENT_DB
+        .get_name_reading(guild_id, state.user_id)
+        .or_else(|| state.member.as_ref().map(|m| m.display_name().to_string()))
+        .or_else(|| {
+            ctx.cache
+                .user(state.user_id)
+                .map(|u| u.global_name.as_ref().unwrap_or(&u.name).clone())
+        })
+        .unwrap_or_else(|| "誰か".to_string())
+}
+
+fn schedule_leave(ctx: Context, guild_id: GuildId, grace: Duration) {
+    let task = tokio::spawn(async move {
+        if !is_deserted(&ctx, guild_id).await {
+            return;
+        }
+
+        tokio::time::sleep(grace).await;
+
+        // Someone may have come back, or the bot may have been moved, in the meantime.
+        if !is_deserted(&ctx, guild_id).await {
+            return;
+        }
+
+        let manager = songbird::get(&ctx)
+            .await
+            .expect("Songbird is not initialized.");
+
+        if manager.leave(guild_id).await.is_ok() {
+            INMEMORY_DB.destroy_instance(guild_id);
+            PERSISTENT_DB.remove_session(guild_id);
+        }
+    });
+
+    INMEMORY_DB.replace_leave(guild_id, task.abort_handle());
+}
+
+/// Rejoins the voice channels the bot was in before restarting.
+/// Sessions which cannot or need not be restored are discarded.
+pub async fn restore_sessions(ctx: &Context) {
+    for (guild_id, session) in PERSISTENT_DB.get_sessions() {
+        if humans_in(ctx, guild_id, session.voice).unwrap_or(0) == 0 {
+            PERSISTENT_DB.remove_session(guild_id);
+            continue;
+        }
+
+        let texts: Vec<ChannelId> = session.texts.into_iter().collect();
+
+        if let Err(e) = join::connect(ctx, guild_id, session.voice, &texts).await {
+            println!("Failed to restore {guild_id}: {}", e.to_message());
+            PERSISTENT_DB.remove_session(guild_id);
+            continue;
+        }
+
+        for text in texts {
+            let _: Result<_, _> = text
+                .say(
+                    &ctx.http,
+                    format!(
+                        "Reconnected after restart. {} <-> {}",
+                        text.mention(),
+                        session.voice.mention()
+                    ),
+                )
+                .await;
+        }
+    }
+}
+
+pub async fn current_channel(ctx: &Context, guild_id: GuildId) -> Option<ChannelId> {
+    let manager = songbird::get(ctx).await?;
+    let channel = manager.get(guild_id)?.lock().await.current_channel()?;
+
+    Some(ChannelId::new(channel.0.get()))
+}
+
+/// Whether the bot is in a voice channel where no human is.
+async fn is_deserted(ctx: &Context, guild_id: GuildId) -> bool {
+    let Some(channel) = current_channel(ctx, guild_id).await else {
+        return false;
+    };
+
+    humans_in(ctx, guild_id, channel) == Some(0)
+}
+
+pub fn humans_in(ctx: &Context, guild_id: GuildId, channel: ChannelId) -> Option<usize> {
+    let users: Vec<UserId> = ctx
+        .cache
+        .guild(guild_id)?
+        .voice_states
+        .values()
+        .filter(|v| v.channel_id == Some(channel))
+        .map(|v| v.user_id)
+        .collect();
+
+    Some(
+        users
+            .into_iter()
+            .filter(|u| !is_bot(ctx, guild_id, *u))
+            .count(),
+    )
+}
+
+/// Unknown users are regarded as humans, so that the bot never leaves them alone by mistake.
+pub fn is_bot(ctx: &Context, guild_id: GuildId, user: UserId) -> bool {
+    ctx.cache
+        .guild(guild_id)
+        .and_then(|g| g.members.get(&user).map(|m| m.user.bot))
+        .or_else(|| ctx.cache.user(user).map(|u| u.bot))
+        .unwrap_or(false)
+}
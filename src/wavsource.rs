@@ -1,6 +1,6 @@
 use std::io::{Read, Result, Seek, SeekFrom};
 
-use hound::WavReader;
+use hound::{SampleFormat, WavReader};
 use symphonia_core::io::MediaSource;
 
 pub struct WavSource<'a> {
@@ -16,22 +16,73 @@ fn completion_24k_to_48k(cum: &mut i16, v: i16) -> Option<[i16; 2]> {
     Some([comp as i16, v])
 }
 
+/// Mixes the channels down and resamples linearly to 48kHz, for engines other than VOICEVOX.
+fn to_48k_mono(data: &[i16], sample_rate: u32, channels: u16) -> Vec<i16> {
+    let channels = channels.max(1);
+    let mono: Vec<i64> = data
+        .chunks_exact(usize::from(channels))
+        .map(|c| c.iter().map(|v| i64::from(*v)).sum::<i64>() / i64::from(channels))
+        .collect();
+
+    let Some(last) = mono.len().checked_sub(1) else {
+        return vec![];
+    };
+
+    let len = u64::try_from(mono.len()).unwrap() * 48000 / u64::from(sample_rate.max(1));
+
+    (0..len)
+        .map(|n| {
+            let pos = n * u64::from(sample_rate);
+            let i = usize::try_from(pos / 48000).map_or(last, |i| i.min(last));
+            let frac = i64::try_from(pos % 48000).unwrap();
+            let (a, b) = (mono[i], mono[(i + 1).min(last)]);
+
+            i16::try_from(a + (b - a) * frac / 48000).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Reads the samples as 16-bit, whatever the format, failing on a broken or truncated file.
+fn read_samples<R: Read>(reader: WavReader<R>) -> hound::Result<Vec<i16>> {
+    let spec = reader.spec();
+
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, bits @ ..=16) => reader
+            .into_samples::<i16>()
+            .map(|v| v.map(|v| v << (16 - bits)))
+            .collect(),
+        (SampleFormat::Int, bits) => reader
+            .into_samples::<i32>()
+            .map(|v| v.map(|v| i16::try_from(v >> (bits - 16)).unwrap_or_default()))
+            .collect(),
+        (SampleFormat::Float, _) => reader
+            .into_samples::<f32>()
+            .map(|v| {
+                #[allow(clippy::cast_possible_truncation)]
+                v.map(|v| (v.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16)
+            })
+            .collect(),
+    }
+}
+
 impl<'a> WavSource<'a> {
-    pub fn new<R: Seek + Read>(reader: &mut R) -> Self {
-        let data: Vec<i16> = WavReader::new(reader)
-            .unwrap()
-            .samples()
-            .map(|v| v.unwrap())
-            .collect();
-
-        Self {
+    pub fn new<R: Seek + Read>(reader: &mut R) -> hound::Result<Self> {
+        let reader = WavReader::new(reader)?;
+        let spec = reader.spec();
+        let data = read_samples(reader)?;
+
+        let samples: Box<dyn Iterator<Item = i16> + Send + Sync> =
+            if spec.sample_rate == 24000 && spec.channels == 1 {
+                Box::new(data.into_iter().scan(0, completion_24k_to_48k).flatten())
+            } else {
+                Box::new(to_48k_mono(&data, spec.sample_rate, spec.channels).into_iter())
+            };
+
+        Ok(Self {
             iterator: Box::new(
-                data.into_iter()
-                    .scan(0, completion_24k_to_48k)
-                    .flatten()
-                    .flat_map(|v| f32::to_le_bytes(f32::from(v) / f32::from(i16::MAX))),
+                samples.flat_map(|v| f32::to_le_bytes(f32::from(v) / f32::from(i16::MAX))),
             ),
-        }
+        })
     }
 }
 
@@ -63,3 +114,40 @@ impl<'a> MediaSource for WavSource<'a> {
         None
     }
 }
+
+#[cfg(test)]
+fn write_wav<S: hound::Sample + Copy>(format: SampleFormat, bits: u16, samples: &[S]) -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: bits,
+        sample_format: format,
+    };
+    let mut cursor = std::io::Cursor::new(vec![]);
+    let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+#[test]
+fn wavsource_unit_test() {
+    use std::io::Cursor;
+
+    let float = write_wav(SampleFormat::Float, 32, &[0.5f32, -2.0]);
+    let samples = read_samples(WavReader::new(Cursor::new(float)).unwrap()).unwrap();
+    assert_eq!(samples, [i16::MAX / 2, -i16::MAX]);
+
+    let pcm24 = write_wav(SampleFormat::Int, 24, &[0x40_0000, -0x80_0000]);
+    let samples = read_samples(WavReader::new(Cursor::new(pcm24)).unwrap()).unwrap();
+    assert_eq!(samples, [0x4000, i16::MIN]);
+
+    let pcm16 = write_wav(SampleFormat::Int, 16, &(0..100i16).collect::<Vec<_>>());
+    assert!(WavSource::new(&mut Cursor::new(&pcm16[..pcm16.len() - 51])).is_err());
+    assert!(WavSource::new(&mut Cursor::new(b"not a wav".to_vec())).is_err());
+    assert!(WavSource::new(&mut Cursor::new(pcm16)).is_ok());
+}